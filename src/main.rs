@@ -1,29 +1,196 @@
+// the modules below are used as a library, most of it is only reached from the tests
+#![allow(dead_code)]
+#![allow(clippy::manual_is_multiple_of)]
 mod sanity_checks {
-    use rand::Rng;
+    use crate::error::SubgroupError;
+    use rand::{Rng, RngCore};
+    #[cfg(test)]
+    use std::cell::Cell;
+    use std::collections::HashMap;
+    use std::sync::OnceLock;
     // check that a number is a factor to another number
     pub fn is_factor(factor: i32, number: i32) -> bool {
         number % factor == 0
     }
+    // greatest common divisor using euclid's algorithm
+    pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+        while b != 0 {
+            let t = a % b;
+            a = b;
+            b = t;
+        }
+        a
+    }
+    // the smallest m with m * m >= n, exact for every u64 unlike rounding the f64 square root
+    // which loses the low bits once n is above 2^53
+    pub fn ceil_sqrt(n: u64) -> u64 {
+        let root = n.isqrt();
+        if root * root < n {
+            root + 1
+        } else {
+            root
+        }
+    }
+    // multiply a and b modulo n, the product is done in u128 so it cannot overflow
+    pub fn mod_mul(a: u64, b: u64, n: u64) -> u64 {
+        let result = ((a as u128 * b as u128) % n as u128) as u64;
+        // debug builds compare against plain u64 arithmetic whenever the plain product fits
+        debug_assert!(checked_mod_mul(a, b, n).is_none_or(|naive| naive == result));
+        result
+    }
+    // a*b mod n in plain u64 arithmetic, None when a*b overflows u64
+    // the overflow is what breaks a naive a * b % n for moduli above 2^32, mod_mul does not have it
+    pub fn checked_mod_mul(a: u64, b: u64, n: u64) -> Option<u64> {
+        a.checked_mul(b).map(|product| product % n)
+    }
     pub fn mod_exp(mut a: u64, mut s: u64, n: u64) -> u64 {
         let mut result = 1;
         a %= n;
         while s > 0 {
             if s % 2 == 1 {
-                result = (result * a) % n;
+                result = mod_mul(result, a, n);
             }
             s /= 2;
-            a = (a * a) % n;
+            a = mod_mul(a, a, n);
         }
         result
     }
+    // mod_exp that also counts the modular multiplications it does, squarings included
+    // one squaring per bit of s plus one multiplication per set bit, so at most 2 * bits(s)
+    pub fn mod_exp_counted(mut a: u64, mut s: u64, n: u64) -> (u64, u32) {
+        let mut result = 1;
+        let mut multiplications = 0;
+        a %= n;
+        while s > 0 {
+            if s % 2 == 1 {
+                result = mod_mul(result, a, n);
+                multiplications += 1;
+            }
+            s /= 2;
+            a = mod_mul(a, a, n);
+            multiplications += 1;
+        }
+        (result, multiplications)
+    }
+    // the inverse of a modulo n from the extended euclidean algorithm, n does not have to be prime
+    // None when a and n are not coprime
+    pub fn mod_inverse(a: u64, n: u64) -> Option<u64> {
+        if n == 0 {
+            return None;
+        }
+        let (mut r0, mut r1) = ((a % n) as i128, n as i128);
+        let (mut s0, mut s1) = (1i128, 0i128);
+        while r1 != 0 {
+            let t = r0 / r1;
+            (r0, r1) = (r1, r0 - t * r1);
+            (s0, s1) = (s1, s0 - t * s1);
+        }
+        if r0 != 1 && n != 1 {
+            return None;
+        }
+        Some(s0.rem_euclid(n as i128) as u64)
+    }
+    // the binomial coefficient n choose k modulo the prime p
+    // by lucas' theorem it is the product of (n_i choose k_i) over the base p digits of n and k, so n can be far larger than p
+    // every digit is below p so the k_i! in the denominator is invertible
+    // p has to be prime, the denominators need not be invertible modulo a composite
+    pub fn binomial_mod(mut n: u64, mut k: u64, p: u64) -> Result<u64, SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        let mut result = 1;
+        while k > 0 {
+            let (n_i, k_i) = (n % p, k % p);
+            if k_i > n_i {
+                return Ok(0);
+            }
+            let k_i = k_i.min(n_i - k_i);
+            let mut numerator = 1;
+            let mut denominator = 1;
+            for j in 0..k_i {
+                numerator = mod_mul(numerator, n_i - j, p);
+                denominator = mod_mul(denominator, j + 1, p);
+            }
+            let inverse = mod_inverse(denominator, p).expect("the digits are below the prime p");
+            let digit = mod_mul(numerator, inverse, p);
+            result = mod_mul(result, digit, p);
+            n /= p;
+            k /= p;
+        }
+        Ok(result)
+    }
+    // b^s mod n for every b in bases
+    // the bits of s are walked once from the top and every base follows the same square and multiply schedule,
+    // which saves the repeated bit scanning but not multiplications, each base still costs what mod_exp does
+    pub fn mod_exp_multibase(bases: &[u64], s: u64, n: u64) -> Vec<u64> {
+        let bases: Vec<u64> = bases.iter().map(|b| b % n).collect();
+        let mut results = vec![1 % n; bases.len()];
+        for bit in (0..64 - s.leading_zeros()).rev() {
+            for (result, &b) in results.iter_mut().zip(&bases) {
+                *result = mod_mul(*result, *result, n);
+                if (s >> bit) & 1 == 1 {
+                    *result = mod_mul(*result, b, n);
+                }
+            }
+        }
+        results
+    }
+    // base^e mod n for every e in exps
+    // the squares base^(2^j) are computed once up to the longest exponent and shared,
+    // so each exponent only costs one multiplication per set bit
+    pub fn mod_exp_batch(base: u64, exps: &[u64], n: u64) -> Vec<u64> {
+        let bits = exps
+            .iter()
+            .map(|e| 64 - e.leading_zeros())
+            .max()
+            .unwrap_or(0);
+        let mut squares = Vec::with_capacity(bits as usize);
+        let mut a = base % n;
+        for _ in 0..bits {
+            squares.push(a);
+            a = mod_mul(a, a, n);
+        }
+        exps.iter()
+            .map(|&e| {
+                let mut result = 1;
+                for (j, &square) in squares.iter().enumerate() {
+                    if (e >> j) & 1 == 1 {
+                        result = mod_mul(result, square, n);
+                    }
+                }
+                result
+            })
+            .collect()
+    }
+    // one round of miller rabin with base a where n - 1 = 2^r * s and s is odd
+    // returns false if a proves that n is composite
+    fn miller_rabin_round(n: u64, a: u64, s: u64, r: u32) -> bool {
+        let mut x = mod_exp(a, s, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..r {
+            x = mod_mul(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    }
     // check if a number is prime using miller rabin algo
     pub fn is_prime(n: u64, k: u64) -> bool {
+        if !rng_available() {
+            return is_prime_deterministic(n);
+        }
         if n <= 1 || n == 4 {
             return false;
         }
         if n <= 3 {
             return true;
         }
+        if n % 2 == 0 {
+            return false;
+        }
 
         let mut r = 0;
         let mut s = n - 1;
@@ -35,46 +202,557 @@ mod sanity_checks {
         let mut rng = rand::thread_rng();
         for _ in 0..k {
             let a: u64 = rng.gen_range(2..n - 2);
-            let mut x = mod_exp(a, s, n);
+            let passed = miller_rabin_round(n, a, s, r);
+            #[cfg(feature = "log")]
+            log::trace!("miller rabin n = {} base = {} passed = {}", n, a, passed);
+            if !passed {
+                return false;
+            }
+        }
+        true
+    }
+    // number of miller rabin rounds used when the caller does not pick one
+    // each round lets a composite through with probability at most 1/4 so 40 rounds is below 2^-80
+    pub const DEFAULT_MR_ROUNDS: u64 = 40;
+    // check if a number is prime using miller rabin with DEFAULT_MR_ROUNDS rounds
+    pub fn is_prime_default(n: u64) -> bool {
+        is_prime(n, DEFAULT_MR_ROUNDS)
+    }
+    // the first 12 primes are enough witnesses for miller rabin to be exact for every n < 3.3 * 10^24
+    // which covers all of u64
+    const DETERMINISTIC_BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    // below 4_759_123_141, which covers all of u32, the three bases 2, 7 and 61 are already exact
+    // 4_759_123_141 = 48781 * 97561 itself is the first composite they let through
+    const SMALL_BASES: [u64; 3] = [2, 7, 61];
+    const SMALL_BASES_LIMIT: u64 = 4_759_123_141;
+    // check if a number is prime using miller rabin with a fixed set of bases, this never gives a wrong answer for u64
+    pub fn is_prime_deterministic(n: u64) -> bool {
+        if n < 2 {
+            return false;
+        }
+        for q in DETERMINISTIC_BASES {
+            if n % q == 0 {
+                return n == q;
+            }
+        }
+
+        let mut r = 0;
+        let mut s = n - 1;
+        while s % 2 == 0 {
+            r += 1;
+            s /= 2;
+        }
+        let bases: &[u64] = if n < SMALL_BASES_LIMIT {
+            &SMALL_BASES
+        } else {
+            &DETERMINISTIC_BASES
+        };
+        // a base that is a multiple of n says nothing, that only happens for n = 61
+        bases
+            .iter()
+            .filter(|&&a| a % n != 0)
+            .all(|&a| miller_rabin_round(n, a, s, r))
+    }
+
+    // jacobi symbol (a/n) for odd n, a is given already reduced modulo n
+    fn jacobi(mut a: u64, mut n: u64) -> i32 {
+        let mut result = 1;
+        while a != 0 {
+            while a % 2 == 0 {
+                a /= 2;
+                if n % 8 == 3 || n % 8 == 5 {
+                    result = -result;
+                }
+            }
+            std::mem::swap(&mut a, &mut n);
+            if a % 4 == 3 && n % 4 == 3 {
+                result = -result;
+            }
+            a %= n;
+        }
+        if n == 1 {
+            result
+        } else {
+            0
+        }
+    }
+    // x / 2 modulo the odd number n
+    fn half_mod(x: u64, n: u64) -> u64 {
+        if x % 2 == 0 {
+            x / 2
+        } else {
+            ((x as u128 + n as u128) / 2) as u64
+        }
+    }
+    // strong lucas probable prime test with the parameters chosen by selfridge's method a
+    // d is the first of 5, -7, 9, -11, ... with jacobi(d/n) = -1, then p = 1 and q = (1 - d) / 4
+    // n has to be odd, greater than 2 and not a perfect square
+    fn is_strong_lucas_probable_prime(n: u64) -> bool {
+        let mut d: i64 = 5;
+        loop {
+            let d_mod_n = (d as i128).rem_euclid(n as i128) as u64;
+            match jacobi(d_mod_n, n) {
+                -1 => break,
+                0 if d.unsigned_abs() != n => return false,
+                _ => {}
+            }
+            d = if d > 0 { -(d + 2) } else { -d + 2 };
+        }
+        let d_mod_n = (d as i128).rem_euclid(n as i128) as u64;
+        let q_mod_n = (((1 - d) / 4) as i128).rem_euclid(n as i128) as u64;
+        let sub = |a: u64, b: u64| if a >= b { a - b } else { a + (n - b) };
+
+        // n + 1 = k * 2^s with k odd, n + 1 is computed in u128 in case n is u64::MAX
+        let mut k = n as u128 + 1;
+        let mut s = 0;
+        while k % 2 == 0 {
+            k /= 2;
+            s += 1;
+        }
+        let k = k as u64;
+
+        // walk the bits of k from the top computing u_k, v_k and q^k
+        let mut u = 1;
+        let mut v = 1;
+        let mut qk = q_mod_n;
+        for bit in (0..63 - k.leading_zeros()).rev() {
+            u = mod_mul(u, v, n);
+            v = sub(mod_mul(v, v, n), mod_mul(2, qk, n));
+            qk = mod_mul(qk, qk, n);
+            if (k >> bit) & 1 == 1 {
+                let new_u = half_mod(((u as u128 + v as u128) % n as u128) as u64, n);
+                let new_v = half_mod(
+                    ((mod_mul(d_mod_n, u, n) as u128 + v as u128) % n as u128) as u64,
+                    n,
+                );
+                u = new_u;
+                v = new_v;
+                qk = mod_mul(qk, q_mod_n, n);
+            }
+        }
+        if u == 0 || v == 0 {
+            return true;
+        }
+        for _ in 1..s {
+            v = sub(mod_mul(v, v, n), mod_mul(2, qk, n));
+            qk = mod_mul(qk, qk, n);
+            if v == 0 {
+                return true;
+            }
+        }
+        false
+    }
+    // baillie psw primality test, a base 2 strong probable prime test followed by a strong lucas test
+    // no composite number is known to pass both and it has been checked to be exact for every u64
+    pub fn is_prime_bpsw(n: u64) -> bool {
+        if n < 2 {
+            return false;
+        }
+        for q in [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37] {
+            if n % q == 0 {
+                return n == q;
+            }
+        }
+
+        let mut r = 0;
+        let mut s = n - 1;
+        while s % 2 == 0 {
+            r += 1;
+            s /= 2;
+        }
+        if !miller_rabin_round(n, 2, s, r) {
+            return false;
+        }
+        // a perfect square never gets a jacobi symbol of -1 so the lucas parameter search would not end
+        let root = (n as f64).sqrt() as u64;
+        if (root.saturating_sub(1)..=root + 1).any(|x| x.checked_mul(x) == Some(n)) {
+            return false;
+        }
+        is_strong_lucas_probable_prime(n)
+    }
+
+    // a number that has been checked to be prime, the check happens once in Prime::new
+    // functions that take a Prime can skip their own primality test
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Prime(u64);
+    impl Prime {
+        pub fn new(p: u64) -> Option<Prime> {
+            if is_prime_deterministic(p) {
+                Some(Prime(p))
+            } else {
+                None
+            }
+        }
+        pub fn get(self) -> u64 {
+            self.0
+        }
+    }
+
+    // whether the operating system can seed a random number generator, only asked once
+    // thread_rng panics when it cannot, so the randomized functions check this first and fall back
+    // to deterministic primality tests and scanning for the smallest primitive root
+    pub fn rng_available() -> bool {
+        #[cfg(test)]
+        {
+            if RNG_DISABLED.with(|disabled| disabled.get()) {
+                return false;
+            }
+        }
+        static AVAILABLE: OnceLock<bool> = OnceLock::new();
+        *AVAILABLE.get_or_init(|| rand::rngs::OsRng.try_fill_bytes(&mut [0; 8]).is_ok())
+    }
+    // lets a test pretend the random number generator cannot be seeded, only on the current thread
+    #[cfg(test)]
+    thread_local! {
+        pub static RNG_DISABLED: Cell<bool> = const { Cell::new(false) };
+    }
+    // the largest bit length random_prime accepts
+    pub fn max_prime_bits() -> u32 {
+        63
+    }
+    // a random prime with exactly the given number of bits, that is in the range 2^(bits-1) <= p < 2^bits
+    // bit lengths below 2 have no primes and above max_prime_bits are not supported
+    pub fn random_prime(bits: u32) -> Result<u64, SubgroupError> {
+        if !(2..=max_prime_bits()).contains(&bits) {
+            return Err(SubgroupError::BitLengthUnsupported);
+        }
+        // without randomness the smallest prime of that bit length is returned, there is always one by bertrand's postulate
+        if !rng_available() {
+            return Ok((1 << (bits - 1)..1 << bits)
+                .find(|&candidate| is_prime_deterministic(candidate))
+                .unwrap());
+        }
+        let mut rng = rand::thread_rng();
+        loop {
+            let candidate = rng.gen_range(1 << (bits - 1)..1 << bits);
+            if is_prime_deterministic(candidate) {
+                return Ok(candidate);
+            }
+        }
+    }
+
+    // remembers the result of is_prime_deterministic so repeated queries skip miller rabin
+    // the map is only used for lookups so its iteration order is never observable
+    #[derive(Debug, Default)]
+    pub struct PrimeCache {
+        known: HashMap<u64, bool>,
+        hits: u64,
+    }
+    impl PrimeCache {
+        pub fn new() -> Self {
+            Self::default()
+        }
+        pub fn is_prime(&mut self, n: u64) -> bool {
+            if let Some(&result) = self.known.get(&n) {
+                self.hits += 1;
+                return result;
+            }
+            let result = is_prime_deterministic(n);
+            self.known.insert(n, result);
+            result
+        }
+        // how many queries were answered from the cache
+        pub fn hits(&self) -> u64 {
+            self.hits
+        }
+        pub fn len(&self) -> usize {
+            self.known.len()
+        }
+        pub fn is_empty(&self) -> bool {
+            self.known.is_empty()
+        }
+    }
+    // a PrimeCache that can be shared between threads
+    #[cfg(feature = "sync")]
+    #[derive(Debug, Default)]
+    pub struct SyncPrimeCache {
+        inner: std::sync::Mutex<PrimeCache>,
+    }
+    #[cfg(feature = "sync")]
+    impl SyncPrimeCache {
+        pub fn new() -> Self {
+            Self::default()
+        }
+        pub fn is_prime(&self, n: u64) -> bool {
+            self.inner.lock().unwrap().is_prime(n)
+        }
+        pub fn hits(&self) -> u64 {
+            self.inner.lock().unwrap().hits()
+        }
+    }
+
+    // a + b modulo n for a, b < n without overflowing u128
+    fn mod_add_u128(a: u128, b: u128, n: u128) -> u128 {
+        if a >= n - b {
+            a - (n - b)
+        } else {
+            a + b
+        }
+    }
+    // multiply a and b modulo n for u128 values
+    // when both fit in 64 bits the product fits in u128, otherwise we fall back to double and add
+    pub fn mod_mul_u128(mut a: u128, mut b: u128, n: u128) -> u128 {
+        a %= n;
+        b %= n;
+        if a <= u64::MAX as u128 && b <= u64::MAX as u128 {
+            return (a * b) % n;
+        }
+        let mut result = 0;
+        while b > 0 {
+            if b & 1 == 1 {
+                result = mod_add_u128(result, a, n);
+            }
+            a = mod_add_u128(a, a, n);
+            b >>= 1;
+        }
+        result
+    }
+    pub fn mod_exp_u128(mut a: u128, mut s: u128, n: u128) -> u128 {
+        let mut result = 1 % n;
+        a %= n;
+        while s > 0 {
+            if s & 1 == 1 {
+                result = mod_mul_u128(result, a, n);
+            }
+            s >>= 1;
+            a = mod_mul_u128(a, a, n);
+        }
+        result
+    }
+    // the first 13 primes as witnesses make miller rabin exact for n < 3317044064679887385961981 (about 2^81)
+    // above that no small deterministic set is known so the answer is a strong probable prime to all 13 bases
+    const DETERMINISTIC_BASES_U128: [u128; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+    // check if a u128 is prime, values that fit in u64 go through is_prime_deterministic
+    pub fn is_prime_u128(n: u128) -> bool {
+        if n <= u64::MAX as u128 {
+            return is_prime_deterministic(n as u64);
+        }
+        for q in DETERMINISTIC_BASES_U128 {
+            if n % q == 0 {
+                return false;
+            }
+        }
+
+        let mut r = 0;
+        let mut s = n - 1;
+        while s % 2 == 0 {
+            r += 1;
+            s /= 2;
+        }
+        'bases: for a in DETERMINISTIC_BASES_U128 {
+            let mut x = mod_exp_u128(a, s, n);
             if x == 1 || x == n - 1 {
                 continue;
             }
-            let mut is_composite = true;
             for _ in 0..r - 1 {
-                x = mod_exp(x, 2, n);
+                x = mod_mul_u128(x, x, n);
                 if x == n - 1 {
-                    is_composite = false;
-                    break;
+                    continue 'bases;
                 }
             }
-            if is_composite {
-                return false;
-            }
+            return false;
         }
         true
     }
 }
 mod primitive_root {
-    use crate::sanity_checks::mod_exp;
+    use crate::sanity_checks::{gcd, is_prime_deterministic, mod_exp};
     // find factors of k
     pub fn factors(k: u64) -> Vec<u64> {
         let mut factors = Vec::new();
-        for i in 1..=k {
+        factors_into(k, &mut factors);
+        factors
+    }
+    // same as factors but fills a buffer owned by the caller so it can be reused between calls
+    // the buffer is cleared first
+    // the divisors are built from the prime factorization, which is quick whenever k has small factors
+    // like p-1 for the usual transform primes, instead of trying every number up to k
+    pub fn factors_into(k: u64, out: &mut Vec<u64>) {
+        out.clear();
+        if k == 0 {
+            return;
+        }
+        out.push(1);
+        for (q, e) in prime_factorization(k) {
+            let len = out.len();
+            let mut power = 1;
+            for _ in 0..e {
+                power *= q;
+                for i in 0..len {
+                    out.push(out[i] * power);
+                }
+            }
+        }
+        out.sort_unstable();
+    }
+    // the divisors of k in ascending order, produced one at a time
+    // the small divisors i <= sqrt(k) are found by trial division as they are needed, their partners k / i are kept
+    // on a stack and handed out largest i first once the small ones run out
+    pub fn factors_iter(k: u64) -> impl Iterator<Item = u64> {
+        let mut i = 0;
+        let mut large = Vec::new();
+        let mut small_done = k == 0;
+        std::iter::from_fn(move || {
+            while !small_done {
+                i += 1;
+                if i > k / i {
+                    small_done = true;
+                } else if k % i == 0 {
+                    if i != k / i {
+                        large.push(k / i);
+                    }
+                    return Some(i);
+                }
+            }
+            large.pop()
+        })
+    }
+    // factorise k into its prime factors with their multiplicity using trial division
+    // returns pairs of (prime, exponent) with the primes in ascending order
+    pub fn prime_factorization(mut k: u64) -> Vec<(u64, u32)> {
+        let mut result = Vec::new();
+        let mut i = 2;
+        // i <= k / i rather than i * i <= k so a large prime k cannot overflow the square
+        // stop early once what is left is prime, otherwise a prime like 2^61 - 1 takes billions of divisions
+        let mut rest_is_prime = is_prime_deterministic(k);
+        while i <= k / i && !rest_is_prime {
             if k % i == 0 {
-                factors.push(i);
+                let mut e = 0;
+                while k % i == 0 {
+                    k /= i;
+                    e += 1;
+                }
+                result.push((i, e));
+                rest_is_prime = is_prime_deterministic(k);
             }
+            i += 1;
         }
-        factors
+        if k > 1 {
+            result.push((k, 1));
+        }
+        result
+    }
+    // the distinct prime factors of k in ascending order
+    pub fn prime_factors(k: u64) -> Vec<u64> {
+        prime_factorization(k).into_iter().map(|(q, _)| q).collect()
+    }
+    // euler's totient, the size of the multiplicative group modulo n
+    pub fn euler_totient(n: u64) -> u64 {
+        let mut result = n;
+        for q in prime_factors(n) {
+            result = result / q * (q - 1);
+        }
+        result
+    }
+    // the mobius function, 0 if n has a square factor and otherwise -1 to the number of prime factors of n
+    pub fn mobius(n: u64) -> i8 {
+        let mut result = 1;
+        for (_, e) in prime_factorization(n) {
+            if e > 1 {
+                return 0;
+            }
+            result = -result;
+        }
+        result
+    }
+    // carmichael's function, the largest order an element of the multiplicative group modulo n can have
+    // it is the lcm of lambda(q^e) over the prime powers of n
+    pub fn carmichael_lambda(n: u64) -> u64 {
+        let mut result = 1;
+        for (q, e) in prime_factorization(n) {
+            let lambda = if q == 2 && e >= 3 {
+                1 << (e - 2)
+            } else {
+                q.pow(e - 1) * (q - 1)
+            };
+            result = result / gcd(result, lambda) * lambda;
+        }
+        result
+    }
+    // carmichael numbers are the composites that pass the fermat test a^(n-1) = 1 for every base a coprime to n
+    // korselt's criterion: n is composite, squarefree and q-1 divides n-1 for every prime q dividing n
+    pub fn is_carmichael(n: u64) -> bool {
+        if n < 2 || is_prime_deterministic(n) {
+            return false;
+        }
+        prime_factorization(n)
+            .into_iter()
+            .all(|(q, e)| e == 1 && (n - 1) % (q - 1) == 0)
+    }
+    // the multiplicative order of a modulo m, the smallest d > 0 with a^d = 1
+    // it divides carmichael_lambda(m) so the divisors of that are tried in ascending order
+    // None if a is not coprime to m
+    pub fn multiplicative_order(a: u64, m: u64) -> Option<u64> {
+        if m == 0 || gcd(a, m) != 1 {
+            return None;
+        }
+        if m == 1 {
+            return Some(1);
+        }
+        factors(carmichael_lambda(m))
+            .into_iter()
+            .find(|&d| mod_exp(a, d, m) == 1)
+    }
+    // check that g has order exactly n modulo p without searching for the order
+    // g^n has to be 1 and g^(n/q) must not be 1 for any prime q dividing n
+    // modulo 1 every g is 1 % 1 = 0, which has order 1
+    pub fn has_order(g: u64, n: u64, p: u64) -> bool {
+        if n == 0 || p == 0 || gcd(g, p) != 1 || mod_exp(g, n, p) != 1 % p {
+            return false;
+        }
+        prime_factors(n)
+            .into_iter()
+            .all(|q| mod_exp(g, n / q, p) != 1 % p)
+    }
+    // the largest order of an element modulo modulus, which is carmichael's lambda
+    pub fn max_element_order(modulus: u64) -> u64 {
+        carmichael_lambda(modulus)
+    }
+    // whether there is a primitive root modulo modulus, that is an element whose order is the size of the whole group
+    // the same answer as is_cyclic_group, found by comparing lambda with phi instead of by the shape of modulus
+    pub fn has_primitive_root(modulus: u64) -> bool {
+        modulus != 0 && max_element_order(modulus) == euler_totient(modulus)
+    }
+    // check if the multiplicative group modulo n is cyclic, which is only the case for n = 1, 2, 4, q^k and 2q^k with q an odd prime
+    pub fn is_cyclic_group(n: u64) -> bool {
+        if n == 0 {
+            return false;
+        }
+        if n <= 4 {
+            return true;
+        }
+        let odd = if n % 2 == 0 { n / 2 } else { n };
+        // an even part of 4 or more means n is 4m with m > 1
+        if odd % 2 == 0 {
+            return false;
+        }
+        prime_factorization(odd).len() == 1
     }
 }
 mod multiplicative_subgruop {
     use crate::error::*;
-    use crate::field::{generate_candidate, is_generator};
-    use crate::primitive_root::factors;
-    use crate::sanity_checks::mod_exp;
+    use crate::field::{
+        generate_candidate, is_primitive_root_with_factors, random_primitive_root,
+        smallest_primitive_root,
+    };
+    use crate::ntt::require_power_of_two;
+    use crate::primitive_root::{
+        factors, multiplicative_order, prime_factorization, prime_factors,
+    };
+    use crate::sanity_checks::{
+        ceil_sqrt, gcd, is_prime_deterministic, is_prime_u128, mod_exp, mod_exp_u128, mod_inverse,
+        mod_mul, mod_mul_u128, rng_available, Prime,
+    };
     use rand::Rng;
-    use std::collections::HashSet;
-    use std::iter::FromIterator;
+    use std::collections::{HashMap, HashSet};
+
+    // statistics about the search for a primitive root done by multiplicative_subgroup_with_stats
+    // candidates_tried counts every random candidate including the one that was accepted
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SearchStats {
+        pub candidates_tried: u32,
+        pub generator: u64,
+    }
 
     // generate the multiplicative subgroup of size n from field modulo p
     // this function returns the multplicative subgroup of size n from field modulo p
@@ -84,173 +762,4746 @@ mod multiplicative_subgruop {
     // then it generates a candidate for the primitive root
     // then it checks if the candidate is a primitive root
     // if the candidate is a primitive root then it returns the multiplicative subgroup
+    // the elements come in power order starting with 1
+    // the edge cases are n = 1 which gives [1] and n = p-1 which gives the whole group {1, ..., p-1}
+    // as the powers of the primitive root itself
+    // kept returning a boxed error so existing callers keep working, the typed SubgroupError converts into the box
+    // through the standard From impl for any error type, so callers that want to match on it use the _with_stats version
     pub fn multiplicative_subgroup(p: u64, n: u64) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
-        if !crate::sanity_checks::is_prime(p, 5) {
-            return Err(Box::new(NotPrimeError));
-        }
-
-        if (p - 1) % n != 0 {
-            return Err(Box::new(NotFactorError));
-        }
+        let (subgroup, _) = multiplicative_subgroup_with_stats(p, n)?;
+        Ok(subgroup)
+    }
+    // same as multiplicative_subgroup for a p already known to be prime, only n is checked
+    pub fn multiplicative_subgroup_from_prime(p: Prime, n: u64) -> Result<Vec<u64>, SubgroupError> {
+        Subgroup::from_prime(p, n).map(|subgroup| subgroup.elements)
+    }
+    // same as multiplicative_subgroup but also reports how many candidates were tried before a primitive root was found
+    pub fn multiplicative_subgroup_with_stats(
+        p: u64,
+        n: u64,
+    ) -> Result<(Vec<u64>, SearchStats), SubgroupError> {
+        let e = projection_exponent(p, n)?;
         // let mut rng = rand::thread_rng();
-        let mut g = generate_candidate(p);
-        while !is_generator(p, g) {
-            g = generate_candidate(p);
+        let mut candidates_tried = 1;
+        // for p = 2 and p = 3 there is nothing to pick from, p-1 is the only primitive root
+        let g = if p <= 3 {
+            p - 1
+        } else if !rng_available() {
+            // no random candidates were tried, the smallest primitive root is used instead
+            candidates_tried = 0;
+            smallest_primitive_root(p).ok_or(SubgroupError::GeneratorNotFound)?
+        } else {
+            // factored once here rather than once per candidate
+            let p_minus_1_factors = prime_factors(p - 1);
+            loop {
+                let g = generate_candidate(p);
+                let accepted = is_primitive_root_with_factors(p, g, &p_minus_1_factors);
+                #[cfg(feature = "log")]
+                log::trace!(
+                    "primitive root candidate p = {} g = {} accepted = {}",
+                    p,
+                    g,
+                    accepted
+                );
+                if accepted {
+                    break g;
+                }
+                candidates_tried += 1;
+            }
+        };
+        // h = g^((p-1)/n) generates the subgroup, every next element is the previous one multiplied by h
+        // so no exponent larger than (p-1)/n is ever formed and the list starts with 1
+        let h = mod_exp(g, e, p);
+        let subgroup = powers(h, n, p);
+        check_subgroup_size(&subgroup, n)?;
+        let stats = SearchStats {
+            candidates_tried,
+            generator: g,
+        };
+        Ok((subgroup, stats))
+    }
+    // the subgroup of size n for n a power of two, built from the roots h, h^2, h^4, .. -1 that repeated squaring of h gives
+    // each root doubles the list by appending it times every element so far, starting from [1] and the root -1
+    // the elements come out in bit reversed power order, h^i is at the index with the bits of i reversed
+    pub fn multiplicative_subgroup_pow2(p: u64, n: u64) -> Result<Vec<u64>, SubgroupError> {
+        let log_n = require_power_of_two(n)?;
+        let e = projection_exponent(p, n)?;
+        let g = smallest_primitive_root(p).ok_or(SubgroupError::NotPrime)?;
+        // roots[j] = h^(2^j), the primitive 2^(log_n - j)-th root
+        let mut roots = Vec::with_capacity(log_n as usize);
+        let mut root = mod_exp(g, e, p);
+        for _ in 0..log_n {
+            roots.push(root);
+            root = mod_mul(root, root, p);
         }
-        let mut subgroup = HashSet::new();
-
-        // we generate element in the subgroup by raising the generator to the power i((p-1)/n) mod p where i is in the range of 1 to n
-        for i in 1..=n {
-            subgroup.insert(mod_exp(g, i * ((p - 1) / n), p));
+        let mut subgroup = Vec::with_capacity(n as usize);
+        subgroup.push(1);
+        for &root in roots.iter().rev() {
+            for i in 0..subgroup.len() {
+                subgroup.push(mod_mul(subgroup[i], root, p));
+            }
         }
-
-        let mut subgroup = Vec::from_iter(subgroup);
-        // rotate the list  until 1 is the first element in the list
-        let index = subgroup.iter().position(|&x| x == 1).unwrap();
-        subgroup.rotate_left(index);
+        check_subgroup_size(&subgroup, n)?;
         Ok(subgroup)
     }
-}
-// this mod is where i put error
-mod error {
-    // custom error if n is not a factor of p-1
-    #[derive(Debug)]
-    pub struct NotFactorError;
-    impl std::error::Error for NotFactorError {
-        fn description(&self) -> &str {
-            "n is not a factor of p-1"
-        }
+    // the subgroup of size n without 1, the n-1 elements h^1 .. h^(n-1) in power order
+    pub fn multiplicative_subgroup_nontrivial(p: u64, n: u64) -> Result<Vec<u64>, SubgroupError> {
+        let (mut subgroup, _) = multiplicative_subgroup_with_stats(p, n)?;
+        // the powers start with h^0 = 1
+        subgroup.remove(0);
+        Ok(subgroup)
     }
-    impl std::fmt::Display for NotFactorError {
-        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-            write!(f, "n is not a factor of p-1")
+    // same as multiplicative_subgroup but gives up after max_attempts random candidates instead of searching until a primitive root turns up
+    pub fn multiplicative_subgroup_try(
+        p: u64,
+        n: u64,
+        max_attempts: u32,
+    ) -> Result<Vec<u64>, SubgroupError> {
+        let e = projection_exponent(p, n)?;
+        if !rng_available() {
+            let g = smallest_primitive_root(p).ok_or(SubgroupError::GeneratorNotFound)?;
+            let subgroup = powers(mod_exp(g, e, p), n, p);
+            check_subgroup_size(&subgroup, n)?;
+            return Ok(subgroup);
         }
+        multiplicative_subgroup_try_with_rng(p, n, max_attempts, &mut rand::thread_rng())
     }
-    //custom error if p is not prime
-    #[derive(Debug)]
-    pub struct NotPrimeError;
-    impl std::error::Error for NotPrimeError {
-        fn description(&self) -> &str {
-            "p is not prime"
-        }
+    // multiplicative_subgroup_try drawing the candidates from rng, a seeded rng makes the search reproducible
+    pub fn multiplicative_subgroup_try_with_rng<R: Rng>(
+        p: u64,
+        n: u64,
+        max_attempts: u32,
+        rng: &mut R,
+    ) -> Result<Vec<u64>, SubgroupError> {
+        let e = projection_exponent(p, n)?;
+        let g = if p <= 3 {
+            p - 1
+        } else {
+            let p_minus_1_factors = prime_factors(p - 1);
+            (0..max_attempts)
+                .map(|_| rng.gen_range(2..p - 1))
+                .find(|&g| is_primitive_root_with_factors(p, g, &p_minus_1_factors))
+                .ok_or(SubgroupError::GeneratorNotFound)?
+        };
+        let subgroup = powers(mod_exp(g, e, p), n, p);
+        check_subgroup_size(&subgroup, n)?;
+        Ok(subgroup)
     }
-    impl std::fmt::Display for NotPrimeError {
-        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-            write!(f, "p is not prime")
+    // the subgroup for every (p, n) in requests, in the same order, each failing or succeeding on its own
+    // with the rayon feature the requests are spread over a thread pool
+    pub fn multiplicative_subgroups_batch(
+        requests: &[(u64, u64)],
+    ) -> Vec<Result<Vec<u64>, SubgroupError>> {
+        let compute = |&(p, n): &(u64, u64)| {
+            multiplicative_subgroup_with_stats(p, n).map(|(subgroup, _)| subgroup)
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            requests.par_iter().map(compute).collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            requests.iter().map(compute).collect()
         }
     }
-}
-mod field {
-    use crate::sanity_checks::mod_exp;
-    use rand::Rng;
-    // generate a random element from a field of modulo p the random element will be tested to be a valid primimitive root
-    // to get a valid candidate the elements are in the range of 2 <= x <= p-2
-    pub fn generate_candidate(p: u64) -> u64 {
-        let mut rng = rand::thread_rng();
-        rng.gen_range(2..p - 1)
+    // a computed subgroup together with what it was computed from
+    // generator is the generator of the subgroup itself, not the primitive root of the whole group
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct SubgroupResult {
+        pub prime: u64,
+        pub order: u64,
+        pub generator: u64,
+        pub elements: Vec<u64>,
     }
-    // check if a number is a primitive root modulo p
-    pub fn is_generator(p: u64, g: u64) -> bool {
-        let mut factors = crate::primitive_root::factors(p - 1);
-        // pop the last element of the factors since it is p-1
-        factors.pop();
-        // remove the first element in factors since it is 1
-        factors.remove(0);
-        // check if g is a primitive root modulo p using the factors
-        // if g^((p-1)/f) mod p == 1 for all factors f of p-1 then g is not a primitive root modulo p
-        for f in factors {
-            if mod_exp(g, (p - 1) / f, p) == 1 {
-                return false;
+    // formats as "⟨2⟩ of order 3 mod 7 = {1, 2, 4}"
+    impl std::fmt::Display for SubgroupResult {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(
+                f,
+                "⟨{}⟩ of order {} mod {} = {{",
+                self.generator, self.order, self.prime
+            )?;
+            for (i, x) in self.elements.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", x)?;
+            }
+            write!(f, "}}")
+        }
+    }
+    fn join_elements(elements: &[u64]) -> String {
+        elements
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+    // the elements as a sage list in power order, "[1, 2, 4]"
+    pub fn to_sage(result: &SubgroupResult) -> String {
+        format!("[{}]", join_elements(&result.elements))
+    }
+    // the elements as a magma set literal, "{ 1, 2, 4 }", magma sets are unordered
+    pub fn to_magma(result: &SubgroupResult) -> String {
+        format!("{{ {} }}", join_elements(&result.elements))
+    }
+    // write the results as csv with the header prime,order,generator,elements
+    // the elements of a row are joined with ; so they stay in one column
+    pub fn write_subgroups_csv<W: std::io::Write>(
+        rows: &[SubgroupResult],
+        mut w: W,
+    ) -> std::io::Result<()> {
+        writeln!(w, "prime,order,generator,elements")?;
+        for row in rows {
+            let elements: Vec<String> = row.elements.iter().map(|x| x.to_string()).collect();
+            writeln!(
+                w,
+                "{},{},{},{}",
+                row.prime,
+                row.order,
+                row.generator,
+                elements.join(";")
+            )?;
+        }
+        Ok(())
+    }
+    // deterministic version of multiplicative_subgroup, the same p and n always give the same output
+    // it uses the smallest primitive root g instead of a random one and returns the powers of h = g^((p-1)/n)
+    // in order starting with 1
+    pub fn multiplicative_subgroup_deterministic(
+        p: u64,
+        n: u64,
+    ) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+        let e = projection_exponent(p, n)?;
+        let g = smallest_primitive_root(p).ok_or(SubgroupError::NotPrime)?;
+        let subgroup = powers(mod_exp(g, e, p), n, p);
+        check_subgroup_size(&subgroup, n)?;
+        Ok(subgroup)
+    }
+    // same as multiplicative_subgroup but with the distinct prime factors of p-1 supplied by the caller, which saves factoring p-1
+    // the factors are checked before they are used, each has to be prime and dividing them all out of p-1 has to leave 1
+    pub fn multiplicative_subgroup_with_factors(
+        p: u64,
+        n: u64,
+        p_minus_1_factors: &[u64],
+    ) -> Result<Vec<u64>, SubgroupError> {
+        let e = projection_exponent(p, n)?;
+        let mut rest = p - 1;
+        for &q in p_minus_1_factors {
+            if !is_prime_deterministic(q) || rest % q != 0 {
+                return Err(SubgroupError::InvalidFactors);
+            }
+            while rest % q == 0 {
+                rest /= q;
+            }
+        }
+        if rest != 1 {
+            return Err(SubgroupError::InvalidFactors);
+        }
+        let g = if p <= 3 {
+            p - 1
+        } else if !rng_available() {
+            (2..p)
+                .find(|&g| is_primitive_root_with_factors(p, g, p_minus_1_factors))
+                .ok_or(SubgroupError::GeneratorNotFound)?
+        } else {
+            loop {
+                let g = generate_candidate(p);
+                if is_primitive_root_with_factors(p, g, p_minus_1_factors) {
+                    break g;
+                }
+            }
+        };
+        let subgroup = powers(mod_exp(g, e, p), n, p);
+        check_subgroup_size(&subgroup, n)?;
+        Ok(subgroup)
+    }
+    // rotate the elements so that anchor comes first, the cyclic order of the rest is kept
+    // returns false and leaves the elements untouched if anchor is not one of them
+    pub fn rotate_subgroup_to(elements: &mut [u64], anchor: u64) -> bool {
+        match elements.iter().position(|&x| x == anchor) {
+            Some(index) => {
+                elements.rotate_left(index);
+                true
+            }
+            None => false,
+        }
+    }
+    // the smallest power of two order 2^k >= at_least that has a subgroup modulo p, that is with 2^k dividing p-1
+    // None if p-1 does not have that many factors of two, useful for picking an ntt size
+    pub fn smallest_power_of_two_order(p: u64, at_least: u64) -> Option<u64> {
+        let size = at_least.checked_next_power_of_two()?;
+        cofactor(p, size).ok()?;
+        Some(size)
+    }
+    // the subgroups of size m and n modulo p intersect in the subgroup of size gcd(m, n)
+    pub fn subgroup_intersection(p: u64, m: u64, n: u64) -> Result<Vec<u64>, SubgroupError> {
+        check_subgroup_params(p, m)?;
+        check_subgroup_params(p, n)?;
+        let d = gcd(m, n);
+        let subgroup = powers(subgroup_generator(p, d)?, d, p);
+        check_subgroup_size(&subgroup, d)?;
+        Ok(subgroup)
+    }
+    // in a cyclic group the subgroup of size m is inside the subgroup of size n exactly when m divides n
+    pub fn is_subgroup_of(m: u64, n: u64) -> bool {
+        m != 0 && n % m == 0
+    }
+    // one subgroup in the lattice returned by subgroup_lattice
+    // contains lists the orders of the smaller subgroups inside this one, the divisors of order other than order itself
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct LatticeNode {
+        pub order: u64,
+        pub generator: u64,
+        pub contains: Vec<u64>,
+    }
+    // every subgroup of the multiplicative group modulo p, one for each divisor of p-1 in ascending order
+    // the generators are powers of the smallest primitive root so the result is the same on every call
+    pub fn subgroup_lattice(p: u64) -> Result<Vec<LatticeNode>, SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        let g = smallest_primitive_root(p).ok_or(SubgroupError::NotPrime)?;
+        let orders = factors(p - 1);
+        orders
+            .iter()
+            .map(|&order| {
+                Ok(LatticeNode {
+                    order,
+                    generator: mod_exp(g, cofactor(p, order)?, p),
+                    contains: orders
+                        .iter()
+                        .copied()
+                        .take_while(|&m| m < order)
+                        .filter(|&m| is_subgroup_of(m, order))
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+    // the product of every element of the subgroup of size n, multiplied out one element at a time
+    // it comes out as 1 for odd n and -1 = p-1 for even n, the generalised wilson theorem
+    pub fn subgroup_product(p: u64, n: u64) -> Result<u64, SubgroupError> {
+        let h = subgroup_generator(p, n)?;
+        Ok(powers(h, n, p)
+            .into_iter()
+            .fold(1, |product, x| mod_mul(product, x, p)))
+    }
+    // the inverse of x inside the subgroup of size n, since x^n = 1 it is x^(n-1)
+    // cheaper than a full field inverse when n is small
+    pub fn subgroup_inverse(x: u64, p: u64, n: u64) -> Result<u64, SubgroupError> {
+        check_subgroup_params(p, n)?;
+        if !is_in_subgroup(x, p, n) {
+            return Err(SubgroupError::NotInSubgroup);
+        }
+        Ok(mod_exp(x, n - 1, p))
+    }
+    // subgroups of size n modulo two different primes are both cyclic of order n, so they are always isomorphic
+    // the only thing to check is that both primes actually have a subgroup of size n
+    pub fn subgroups_isomorphic(p1: u64, p2: u64, n: u64) -> Result<bool, SubgroupError> {
+        check_subgroup_params(p1, n)?;
+        check_subgroup_params(p2, n)?;
+        Ok(true)
+    }
+    // compare two subgroups modulo p as sets, ignoring the order of the elements
+    // useful when the result should not depend on which generator was picked
+    pub fn subgroups_equal(a: &[u64], b: &[u64], p: u64) -> bool {
+        let mut a: Vec<u64> = a.iter().map(|x| x % p).collect();
+        let mut b: Vec<u64> = b.iter().map(|x| x % p).collect();
+        a.sort_unstable();
+        a.dedup();
+        b.sort_unstable();
+        b.dedup();
+        a == b
+    }
+    // the cyclic subgroup generated by a, the powers 1, a, a^2, ... up to the order of a
+    pub fn generated_subgroup(a: u64, p: u64) -> Result<Vec<u64>, SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        let order = multiplicative_order(a % p, p).ok_or(SubgroupError::ZeroElement)?;
+        Ok(powers(a % p, order, p))
+    }
+    // the smallest subgroup containing every one of elements, in ascending order
+    // a breadth first search from 1 multiplies each new element by each of elements until nothing new turns up,
+    // which is the closure as every element has finite order. no elements gives the trivial subgroup [1]
+    pub fn subgroup_closure(elements: &[u64], p: u64) -> Result<Vec<u64>, SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        let generators: Vec<u64> = elements.iter().map(|&x| x % p).collect();
+        if generators.contains(&0) {
+            return Err(SubgroupError::ZeroElement);
+        }
+        let mut seen = HashSet::from([1]);
+        let mut queue = std::collections::VecDeque::from([1]);
+        while let Some(x) = queue.pop_front() {
+            for &g in &generators {
+                let product = mod_mul(x, g, p);
+                if seen.insert(product) {
+                    queue.push_back(product);
+                }
+            }
+        }
+        let mut closure: Vec<u64> = seen.into_iter().collect();
+        closure.sort_unstable();
+        Ok(closure)
+    }
+    // the first n powers of h modulo p, 1, h, h^2, ..., h^(n-1)
+    pub fn powers(h: u64, n: u64, p: u64) -> Vec<u64> {
+        let mut result = Vec::with_capacity(n as usize);
+        let mut x = 1;
+        for _ in 0..n {
+            result.push(x);
+            x = mod_mul(x, h, p);
+        }
+        result
+    }
+    // the smallest prime p >= 2^min_bits of the form k * order + 1, so that p has a subgroup of size order
+    // None if order is 0 or the search runs past u64
+    pub fn find_prime_with_subgroup(order: u64, min_bits: u32) -> Option<u64> {
+        if order == 0 {
+            return None;
+        }
+        let min = 1u64.checked_shl(min_bits)?;
+        // the first k with k * order + 1 >= min
+        let mut k = (min - 1).div_ceil(order);
+        loop {
+            let p = k.checked_mul(order)?.checked_add(1)?;
+            if is_prime_deterministic(p) {
+                return Some(p);
+            }
+            k += 1;
+        }
+    }
+    // the subgroup of size n in power order laid out row by row in a matrix with cols columns
+    // used by four step ntt algorithms, cols has to divide n
+    pub fn subgroup_matrix(p: u64, n: u64, cols: usize) -> Result<Vec<Vec<u64>>, SubgroupError> {
+        let h = subgroup_generator(p, n)?;
+        if cols == 0 || n % cols as u64 != 0 {
+            return Err(SubgroupError::InvalidShape);
+        }
+        Ok(powers(h, n, p)
+            .chunks(cols)
+            .map(|row| row.to_vec())
+            .collect())
+    }
+    // the subgroup whose order q is the largest prime dividing p-1, this is the usual schnorr group
+    // returns q together with the subgroup in power order
+    pub fn prime_order_subgroup(p: u64) -> Result<(u64, Vec<u64>), SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        // p = 2 is the only prime where p-1 has no prime factor
+        let q = *prime_factors(p - 1)
+            .last()
+            .ok_or(SubgroupError::NotFactor)?;
+        let h = subgroup_generator(p, q)?;
+        Ok((q, powers(h, q, p)))
+    }
+    // a schnorr prime for q is a prime p = q*r + 1 with q prime, so p has a subgroup of prime order q
+    pub fn is_schnorr_prime(p: u64, q: u64) -> bool {
+        is_prime_deterministic(p) && is_prime_deterministic(q) && (p - 1) % q == 0
+    }
+    // random schnorr parameters (p, q, g) with q a q_bits prime, p = q*r + 1 a p_bits prime and g a generator of the subgroup of order q
+    // None when 2 <= q_bits < p_bits <= 63 does not hold or no r gave a prime within a fixed number of tries
+    pub fn generate_schnorr_params(
+        q_bits: u32,
+        p_bits: u32,
+        rng: &mut impl Rng,
+    ) -> Option<(u64, u64, u64)> {
+        const MAX_ATTEMPTS: u32 = 10_000;
+        if q_bits < 2 || q_bits >= p_bits || p_bits > 63 {
+            return None;
+        }
+        let q = loop {
+            let candidate = rng.gen_range(1 << (q_bits - 1)..1u64 << q_bits);
+            if is_prime_deterministic(candidate) {
+                break candidate;
+            }
+        };
+        // the r that keep p = q*r + 1 within p_bits bits
+        let r_min = ((1u64 << (p_bits - 1)) - 1).div_ceil(q);
+        let r_max = ((1u64 << p_bits) - 2) / q;
+        if r_min > r_max {
+            return None;
+        }
+        let p = (0..MAX_ATTEMPTS)
+            .map(|_| q * rng.gen_range(r_min..=r_max) + 1)
+            .find(|&p| is_prime_deterministic(p))?;
+        // any h^((p-1)/q) other than 1 has order q since q is prime
+        let g = loop {
+            let g = mod_exp(rng.gen_range(2..p - 1), (p - 1) / q, p);
+            if g != 1 {
+                break g;
+            }
+        };
+        Some((p, q, g))
+    }
+    // same as multiplicative_subgroup but returns the subgroup as a SubgroupResult
+    pub fn multiplicative_subgroup_result(
+        p: u64,
+        n: u64,
+    ) -> Result<SubgroupResult, Box<dyn std::error::Error>> {
+        let (elements, stats) = multiplicative_subgroup_with_stats(p, n)?;
+        Ok(SubgroupResult {
+            prime: p,
+            order: n,
+            generator: mod_exp(stats.generator, cofactor(p, n)?, p),
+            elements,
+        })
+    }
+    // check that there is a subgroup of size n modulo p, p has to be prime and n has to divide p-1
+    pub fn check_subgroup_params(p: u64, n: u64) -> Result<(), SubgroupError> {
+        projection_exponent(p, n).map(|_| ())
+    }
+    // check that a generated subgroup really has the n distinct elements that were asked for
+    // run by every constructor after generation, where check_subgroup_params runs before it
+    // a generator of too small an order repeats elements, so the distinct ones are counted rather than the list
+    pub fn check_subgroup_size<T: Eq + std::hash::Hash>(
+        subgroup: &[T],
+        n: u64,
+    ) -> Result<(), SubgroupError> {
+        let distinct = subgroup.iter().collect::<HashSet<_>>().len() as u64;
+        if subgroup.len() as u64 != n || distinct != n {
+            return Err(SubgroupError::WrongSize);
+        }
+        Ok(())
+    }
+    // check if x is in the subgroup of size n modulo p, the elements of that subgroup are exactly the solutions of x^n = 1
+    pub fn is_in_subgroup(x: u64, p: u64, n: u64) -> bool {
+        p != 0 && x % p != 0 && mod_exp(x, n, p) == 1
+    }
+    // the steps behind a subgroup of size n modulo p, as returned by explain_subgroup
+    // primitive_root is the smallest primitive root g, cofactor is e = (p-1)/n and subgroup_generator is h = g^e
+    // h has order n because h^n = g^(p-1) = 1 (generator_to_order) and h^(n/q) != 1 for every prime q dividing n (order_checks)
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct SubgroupExplanation {
+        pub prime: u64,
+        pub order: u64,
+        pub primitive_root: u64,
+        pub cofactor: u64,
+        pub subgroup_generator: u64,
+        pub generator_to_order: u64,
+        pub order_checks: Vec<OrderCheck>,
+    }
+    // one of the checks that h does not have a smaller order, h^exponent = value with exponent = n/prime_factor
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct OrderCheck {
+        pub prime_factor: u64,
+        pub exponent: u64,
+        pub value: u64,
+    }
+    impl SubgroupExplanation {
+        // true when all the checks came out as they should, so the subgroup generator has order exactly n
+        pub fn verified(&self) -> bool {
+            self.generator_to_order == 1 && self.order_checks.iter().all(|check| check.value != 1)
+        }
+    }
+    pub fn explain_subgroup(p: u64, n: u64) -> Result<SubgroupExplanation, SubgroupError> {
+        let cofactor = projection_exponent(p, n)?;
+        let g = smallest_primitive_root(p).ok_or(SubgroupError::NotPrime)?;
+        let h = mod_exp(g, cofactor, p);
+        let order_checks = prime_factors(n)
+            .into_iter()
+            .map(|q| OrderCheck {
+                prime_factor: q,
+                exponent: n / q,
+                value: mod_exp(h, n / q, p),
+            })
+            .collect();
+        Ok(SubgroupExplanation {
+            prime: p,
+            order: n,
+            primitive_root: g,
+            cofactor,
+            subgroup_generator: h,
+            generator_to_order: mod_exp(h, n, p),
+            order_checks,
+        })
+    }
+    // the cofactor (p-1)/n, raising a primitive root to it gives a generator of the subgroup of size n
+    pub fn projection_exponent(p: u64, n: u64) -> Result<u64, SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        cofactor(p, n)
+    }
+    // projection_exponent without the primality test, for callers that have already settled that p is prime
+    // this is the one place that checks n divides p-1, anything else is NotFactor
+    pub fn cofactor(p: u64, n: u64) -> Result<u64, SubgroupError> {
+        if p < 2 || n == 0 || (p - 1) % n != 0 {
+            return Err(SubgroupError::NotFactor);
+        }
+        Ok((p - 1) / n)
+    }
+    // a generator of the subgroup of size n modulo p, g^((p-1)/n) for a random primitive root g
+    pub fn subgroup_generator(p: u64, n: u64) -> Result<u64, SubgroupError> {
+        let e = projection_exponent(p, n)?;
+        Ok(mod_exp(random_primitive_root(p)?, e, p))
+    }
+    // every element of 1..p that is not in the subgroup of size n, in ascending order, p-1-n of them
+    // membership is the test x^n = 1, so the subgroup itself is never built
+    pub fn subgroup_complement(p: u64, n: u64) -> Result<Vec<u64>, SubgroupError> {
+        check_subgroup_params(p, n)?;
+        Ok((1..p).filter(|&x| !is_in_subgroup(x, p, n)).collect())
+    }
+    // a uniformly random element of the subgroup of size n, h^i for a random i in 0..n
+    // h comes from the smallest primitive root so all the randomness is taken from rng and a seeded rng repeats its draws
+    pub fn random_subgroup_element(
+        p: u64,
+        n: u64,
+        rng: &mut impl Rng,
+    ) -> Result<u64, SubgroupError> {
+        let e = projection_exponent(p, n)?;
+        let g = smallest_primitive_root(p).ok_or(SubgroupError::NotPrime)?;
+        Ok(mod_exp(mod_exp(g, e, p), rng.gen_range(0..n), p))
+    }
+    // the smallest element of the coset x*H where H is the subgroup of size n modulo p
+    // every element of the same coset gives the same representative, the cosets are never built
+    pub fn coset_representative(x: u64, p: u64, n: u64) -> Result<u64, SubgroupError> {
+        let h = subgroup_generator(p, n)?;
+        if x % p == 0 {
+            return Err(SubgroupError::ZeroElement);
+        }
+        let mut smallest = x % p;
+        let mut y = smallest;
+        for _ in 1..n {
+            y = mod_mul(y, h, p);
+            smallest = smallest.min(y);
+        }
+        Ok(smallest)
+    }
+    // the subgroup of size n in power order together with one element from each of the other (p-1)/n - 1 cosets
+    // with k = (p-1)/n and g a primitive root the cosets are g^i * H for i < k, the representatives are g^1 .. g^(k-1)
+    pub fn subgroup_and_coset_reps(p: u64, n: u64) -> Result<(Vec<u64>, Vec<u64>), SubgroupError> {
+        let k = projection_exponent(p, n)?;
+        let g = random_primitive_root(p)?;
+        let subgroup = powers(mod_exp(g, k, p), n, p);
+        let reps = powers(g, k, p).into_iter().skip(1).collect();
+        Ok((subgroup, reps))
+    }
+    // the coset c*H of the subgroup H of size n, every element of H multiplied by c in the power order of H
+    // c has to be coprime to p, which for a prime p means nonzero modulo p
+    pub fn coset(p: u64, n: u64, c: u64) -> Result<Vec<u64>, SubgroupError> {
+        let h = subgroup_generator(p, n)?;
+        if gcd(c, p) != 1 {
+            return Err(SubgroupError::ZeroElement);
+        }
+        Ok(powers(h, n, p)
+            .into_iter()
+            .map(|x| mod_mul(c, x, p))
+            .collect())
+    }
+    // map any nonzero element a into the subgroup of size n by raising it to the cofactor (p-1)/n
+    pub fn project_into_subgroup(a: u64, p: u64, n: u64) -> Result<u64, SubgroupError> {
+        let e = projection_exponent(p, n)?;
+        if a % p == 0 {
+            return Err(SubgroupError::ZeroElement);
+        }
+        Ok(mod_exp(a, e, p))
+    }
+    // the number of elements the subgroup of size n modulo p will have, checked without computing anything
+    // that is n itself once p and n are valid, so it can be used to allocate or to turn down large requests up front
+    pub fn subgroup_size(p: u64, n: u64) -> Result<u64, SubgroupError> {
+        check_subgroup_params(p, n)?;
+        Ok(n)
+    }
+    // builds a subgroup with options that multiplicative_subgroup does not take
+    // SubgroupBuilder::new(p, n).primality_test(|p| my_test(p)).build()
+    pub struct SubgroupBuilder {
+        p: u64,
+        n: u64,
+        primality_test: Option<Box<dyn Fn(u64) -> bool>>,
+        max_size: Option<u64>,
+    }
+    impl SubgroupBuilder {
+        pub fn new(p: u64, n: u64) -> Self {
+            SubgroupBuilder {
+                p,
+                n,
+                primality_test: None,
+                max_size: None,
+            }
+        }
+        // a builder for a p already known to be prime, a later primality_test call replaces the check again
+        pub fn from_prime(p: Prime, n: u64) -> Self {
+            Self::new(p.get(), n).primality_test(|_| true)
+        }
+        // refuse with TooLarge instead of building a subgroup with more than limit elements, there is no limit by default
+        pub fn max_reasonable_size(mut self, limit: u64) -> Self {
+            self.max_size = Some(limit);
+            self
+        }
+        // use this predicate to decide if p is prime instead of the deterministic miller rabin
+        // the predicate is trusted, if it says a composite p is prime the build fails with WrongSize or GeneratorNotFound
+        pub fn primality_test(mut self, test: impl Fn(u64) -> bool + 'static) -> Self {
+            self.primality_test = Some(Box::new(test));
+            self
+        }
+        // the subgroup in power order starting with 1
+        pub fn build(self) -> Result<Vec<u64>, SubgroupError> {
+            let (p, n) = (self.p, self.n);
+            let is_prime = match &self.primality_test {
+                Some(test) => test(p),
+                None => is_prime_deterministic(p),
+            };
+            // checked even with a custom test, below 2 there is no p-1 to work with
+            if p < 2 || !is_prime {
+                return Err(SubgroupError::NotPrime);
+            }
+            let e = cofactor(p, n)?;
+            if self.max_size.is_some_and(|limit| n > limit) {
+                return Err(SubgroupError::TooLarge);
+            }
+            let g = random_primitive_root(p)?;
+            let subgroup = powers(mod_exp(g, e, p), n, p);
+            check_subgroup_size(&subgroup, n)?;
+            Ok(subgroup)
+        }
+    }
+    // the balanced representative of x modulo p, in (-p/2, p/2] instead of [0, p)
+    // residues above p/2 become x - p, which always fits in i64 as its absolute value is below p/2
+    // there are no residues modulo 0 so p = 0 is an error
+    pub fn to_centered(x: u64, p: u64) -> Result<i64, SubgroupError> {
+        if p == 0 {
+            return Err(SubgroupError::NotPrime);
+        }
+        let x = x % p;
+        Ok(if x > p / 2 {
+            (x as i128 - p as i128) as i64
+        } else {
+            x as i64
+        })
+    }
+    // the elements of the subgroup of size n modulo p in power order, together with p, n and the generator
+    // derefs to a slice so it can be indexed and iterated like the Vec multiplicative_subgroup returns
+    // members holds the same elements as a set for contains, it is built once when the subgroup is made
+    #[derive(Debug, Clone)]
+    pub struct Subgroup {
+        elements: Vec<u64>,
+        members: HashSet<u64>,
+        p: u64,
+        n: u64,
+        generator: u64,
+    }
+    impl Subgroup {
+        pub fn new(p: u64, n: u64) -> Result<Self, SubgroupError> {
+            Self::from_prime(Prime::new(p).ok_or(SubgroupError::NotPrime)?, n)
+        }
+        // same as new but p is already known to be prime so only n is checked
+        pub fn from_prime(p: Prime, n: u64) -> Result<Self, SubgroupError> {
+            let p = p.get();
+            let e = cofactor(p, n)?;
+            let generator = mod_exp(random_primitive_root(p)?, e, p);
+            let elements = powers(generator, n, p);
+            check_subgroup_size(&elements, n)?;
+            Ok(Self::from_raw_parts(p, n, generator, elements))
+        }
+        // put a subgroup together from parts computed elsewhere, nothing is checked so use validate on the result
+        pub fn from_raw_parts(p: u64, n: u64, generator: u64, elements: Vec<u64>) -> Self {
+            Subgroup {
+                members: elements.iter().copied().collect(),
+                elements,
+                p,
+                n,
+                generator,
+            }
+        }
+        // whether x is one of the elements, a hash lookup instead of the exponentiation is_in_subgroup does
+        // x is taken modulo p
+        pub fn contains(&self, x: u64) -> bool {
+            self.p != 0 && self.members.contains(&(x % self.p))
+        }
+        // check everything a subgroup has to satisfy and collect a message for each check that fails
+        // for closure only the first product that falls outside is reported
+        pub fn validate(&self) -> Result<(), Vec<String>> {
+            let p = self.p;
+            let mut problems = Vec::new();
+            if !self.elements.contains(&1) {
+                problems.push("1 is not an element".to_string());
+            }
+            if self.elements.len() as u64 != self.n {
+                problems.push(format!(
+                    "there are {} elements instead of n = {}",
+                    self.elements.len(),
+                    self.n
+                ));
+            }
+            let mut seen = HashMap::new();
+            for &x in &self.elements {
+                *seen.entry(x).or_insert(0) += 1;
+            }
+            let mut repeated: Vec<u64> = seen
+                .iter()
+                .filter(|&(_, &count)| count > 1)
+                .map(|(&x, _)| x)
+                .collect();
+            repeated.sort_unstable();
+            for x in repeated {
+                problems.push(format!("{} appears {} times", x, seen[&x]));
+            }
+            'closure: for &a in &self.elements {
+                for &b in &self.elements {
+                    let product = mod_mul(a, b, p);
+                    if !seen.contains_key(&product) {
+                        problems.push(format!(
+                            "{} * {} = {} mod {} is not an element",
+                            a, b, product, p
+                        ));
+                        break 'closure;
+                    }
+                }
+            }
+            let g = self.generator;
+            let has_order_n = self.n > 0
+                && g % p != 0
+                && mod_exp(g, self.n, p) == 1
+                && prime_factors(self.n)
+                    .into_iter()
+                    .all(|q| mod_exp(g, self.n / q, p) != 1);
+            if !has_order_n {
+                problems.push(format!(
+                    "the generator {} does not have order {}",
+                    g, self.n
+                ));
+            }
+            if problems.is_empty() {
+                Ok(())
+            } else {
+                Err(problems)
+            }
+        }
+        pub fn p(&self) -> u64 {
+            self.p
+        }
+        pub fn n(&self) -> u64 {
+            self.n
+        }
+        pub fn generator(&self) -> u64 {
+            self.generator
+        }
+        pub fn len(&self) -> usize {
+            self.elements.len()
+        }
+        pub fn is_empty(&self) -> bool {
+            self.elements.is_empty()
+        }
+        // the elements in ascending order
+        pub fn into_sorted_vec(self) -> Vec<u64> {
+            let mut elements = self.elements;
+            elements.sort_unstable();
+            elements
+        }
+        // the elements as they are stored, 1, g, g^2, ... for the generator g
+        pub fn into_power_ordered_vec(self) -> Vec<u64> {
+            self.elements
+        }
+        // the elements in power order mapped to their balanced representatives by to_centered
+        pub fn centered(&self) -> Result<Vec<i64>, SubgroupError> {
+            self.elements
+                .iter()
+                .map(|&x| to_centered(x, self.p))
+                .collect()
+        }
+    }
+    // two subgroups are equal when they have the same p and the same set of elements, the generator and the order
+    // the elements come in do not matter, so Subgroup::new(p, n) twice gives equal values and they can be used as cache keys
+    // hashing sorts a copy of the elements to agree with that
+    impl PartialEq for Subgroup {
+        fn eq(&self, other: &Self) -> bool {
+            self.p == other.p && subgroups_equal(&self.elements, &other.elements, self.p)
+        }
+    }
+    impl Eq for Subgroup {}
+    impl std::hash::Hash for Subgroup {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            let mut elements: Vec<u64> = self.elements.iter().map(|x| x % self.p).collect();
+            elements.sort_unstable();
+            elements.dedup();
+            self.p.hash(state);
+            elements.hash(state);
+        }
+    }
+    // Subgroup::try_from((p, n)), the same as Subgroup::new(p, n)
+    impl TryFrom<(u64, u64)> for Subgroup {
+        type Error = SubgroupError;
+        fn try_from((p, n): (u64, u64)) -> Result<Self, SubgroupError> {
+            Subgroup::new(p, n)
+        }
+    }
+    impl std::ops::Deref for Subgroup {
+        type Target = [u64];
+        fn deref(&self) -> &[u64] {
+            &self.elements
+        }
+    }
+    impl AsRef<[u64]> for Subgroup {
+        fn as_ref(&self) -> &[u64] {
+            &self.elements
+        }
+    }
+    // gives Subgroup an Into<Vec<u64>> for code written against the Vec that multiplicative_subgroup returns
+    impl From<Subgroup> for Vec<u64> {
+        fn from(subgroup: Subgroup) -> Vec<u64> {
+            subgroup.elements
+        }
+    }
+    impl<I: std::slice::SliceIndex<[u64]>> std::ops::Index<I> for Subgroup {
+        type Output = I::Output;
+        fn index(&self, i: I) -> &I::Output {
+            &self.elements[i]
+        }
+    }
+    impl IntoIterator for Subgroup {
+        type Item = u64;
+        type IntoIter = std::vec::IntoIter<u64>;
+        fn into_iter(self) -> Self::IntoIter {
+            self.elements.into_iter()
+        }
+    }
+    impl<'a> IntoIterator for &'a Subgroup {
+        type Item = &'a u64;
+        type IntoIter = std::slice::Iter<'a, u64>;
+        fn into_iter(self) -> Self::IntoIter {
+            self.elements.iter()
+        }
+    }
+    // a subgroup of size n modulo p described by its generator, without materialising the elements
+    // the fields are private so every context comes from new, index_of divides by n and needs it to be valid
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SubgroupContext {
+        p: u64,
+        n: u64,
+        generator: u64,
+    }
+    impl SubgroupContext {
+        pub fn new(p: u64, n: u64) -> Result<Self, SubgroupError> {
+            Self::from_prime(Prime::new(p).ok_or(SubgroupError::NotPrime)?, n)
+        }
+        // same as new but p is already known to be prime so only n is checked
+        pub fn from_prime(p: Prime, n: u64) -> Result<Self, SubgroupError> {
+            let p = p.get();
+            let e = cofactor(p, n)?;
+            Ok(SubgroupContext {
+                p,
+                n,
+                generator: mod_exp(random_primitive_root(p)?, e, p),
+            })
+        }
+        pub fn p(&self) -> u64 {
+            self.p
+        }
+        pub fn n(&self) -> u64 {
+            self.n
+        }
+        pub fn generator(&self) -> u64 {
+            self.generator
+        }
+        // the element with index i, generator^i
+        pub fn element_at(&self, i: u64) -> u64 {
+            mod_exp(self.generator, i, self.p)
+        }
+        // the index of x, the discrete log of x to the base generator inside the subgroup
+        // uses baby step giant step so it takes about sqrt(n) time and memory, None if x is not in the subgroup
+        pub fn index_of(&self, x: u64) -> Option<u64> {
+            let (p, n, h) = (self.p, self.n, self.generator);
+            let x = x % p;
+            if x == 0 {
+                return None;
+            }
+            let m = ceil_sqrt(n);
+            // only ever looked up, never iterated, so the hash order cannot leak into the result
+            let mut baby_steps = HashMap::with_capacity(m as usize);
+            let mut y = 1;
+            for j in 0..m {
+                baby_steps.entry(y).or_insert(j);
+                y = mod_mul(y, h, p);
+            }
+            // h^-m, since h^n = 1 this is h^(n - m mod n)
+            let giant_step = mod_exp(h, (n - m % n) % n, p);
+            let mut gamma = x;
+            for i in 0..m {
+                if let Some(&j) = baby_steps.get(&gamma) {
+                    let index = i * m + j;
+                    return if index < n { Some(index) } else { None };
+                }
+                gamma = mod_mul(gamma, giant_step, p);
+            }
+            None
+        }
+    }
+    // the discrete log of target to the base h, where h generates the subgroup of size n modulo p
+    // pohlig hellman: the log is found modulo every prime power q^e dividing n one base q digit at a time,
+    // each digit with baby step giant step in the subgroup of size q, and the pieces are put together with the crt
+    // that takes about the sum of sqrt(q) * e steps instead of sqrt(n), so it is fast when n is smooth
+    // None if target is not a power of h
+    pub fn discrete_log_subgroup(h: u64, target: u64, p: u64, n: u64) -> Option<u64> {
+        if p < 2 || n == 0 || h % p == 0 || target % p == 0 {
+            return None;
+        }
+        let mut x = 0;
+        let mut modulus = 1;
+        for (q, e) in prime_factorization(n) {
+            let qe = q.pow(e);
+            // h_q has order q^e, and gamma = h_q^(q^(e-1)) has order q
+            let h_q = mod_exp(h, n / qe, p);
+            let t_q = mod_exp(target, n / qe, p);
+            let digits = SubgroupContext {
+                p,
+                n: q,
+                generator: mod_exp(h_q, qe / q, p),
+            };
+            let mut x_q = 0;
+            let mut q_k = 1;
+            for _ in 0..e {
+                // strip the digits found so far and push the next one down into the subgroup of size q
+                let rest = mod_mul(mod_exp(h_q, qe - x_q, p), t_q, p);
+                let digit = digits.index_of(mod_exp(rest, qe / q_k / q, p))?;
+                x_q += digit * q_k;
+                q_k *= q;
+            }
+            x = crt_pair(x, modulus, x_q, qe);
+            modulus *= qe;
+        }
+        (mod_exp(h, x, p) == target % p).then_some(x)
+    }
+    // the x < m1 * m2 with x = a1 mod m1 and x = a2 mod m2, m1 and m2 have to be coprime
+    fn crt_pair(a1: u64, m1: u64, a2: u64, m2: u64) -> u64 {
+        let inverse = mod_inverse(m1, m2).expect("the moduli are coprime") as u128;
+        let difference = (a2 as i128 - a1 as i128).rem_euclid(m2 as i128) as u128;
+        let t = difference * inverse % m2 as u128;
+        (a1 as u128 + m1 as u128 * t) as u64
+    }
+    // raise h^i to the power exp where h generates the subgroup of size n modulo p
+    // the result stays in the subgroup so we return it together with its index (i * exp) mod n
+    // p = 0 and n = 0 describe no subgroup and are errors
+    pub fn subgroup_pow(
+        h: u64,
+        i: u64,
+        exp: u64,
+        p: u64,
+        n: u64,
+    ) -> Result<(u64, u64), SubgroupError> {
+        if p == 0 {
+            return Err(SubgroupError::NotPrime);
+        }
+        if n == 0 {
+            return Err(SubgroupError::NotFactor);
+        }
+        let index = ((i as u128 * exp as u128) % n as u128) as u64;
+        Ok((mod_exp(h, index, p), index))
+    }
+    // (h^i)^exp where h generates the subgroup of size n modulo p
+    // the exponent is reduced modulo the order n first so a huge exp costs no more than a small one
+    pub fn mod_exp_in_subgroup(
+        h: u64,
+        i: u64,
+        exp: u64,
+        p: u64,
+        n: u64,
+    ) -> Result<u64, SubgroupError> {
+        if n == 0 {
+            return Err(SubgroupError::NotFactor);
+        }
+        Ok(subgroup_pow(h, i, exp % n, p, n)?.0)
+    }
+    // generate the multiplicative subgroup of size n modulo a prime p that does not fit in u64
+    // factoring p - 1 is not practical at this size so instead of looking for a primitive root
+    // we raise random elements to (p-1)/n until the result has order exactly n, which only needs the factors of n
+    // the elements are returned in power order starting with 1
+    pub fn multiplicative_subgroup_u128(
+        p: u128,
+        n: u64,
+    ) -> Result<Vec<u128>, Box<dyn std::error::Error>> {
+        if !is_prime_u128(p) {
+            return Err(Box::new(SubgroupError::NotPrime));
+        }
+        if n == 0 || (p - 1) % n as u128 != 0 {
+            return Err(Box::new(SubgroupError::NotFactor));
+        }
+        let cofactor = (p - 1) / n as u128;
+        let order_factors = prime_factors(n);
+        // without randomness the candidates are tried in order from 2
+        let random = rng_available();
+        let mut next = 1;
+        let h = loop {
+            next += 1;
+            let candidate = if p <= 3 {
+                p - 1
+            } else if random {
+                rand::thread_rng().gen_range(2..p - 1)
+            } else {
+                next
+            };
+            let h = mod_exp_u128(candidate, cofactor, p);
+            if order_factors
+                .iter()
+                .all(|&q| mod_exp_u128(h, (n / q) as u128, p) != 1)
+            {
+                break h;
+            }
+        };
+
+        let mut subgroup = Vec::with_capacity(n as usize);
+        let mut x = 1;
+        for _ in 0..n {
+            subgroup.push(x);
+            x = mod_mul_u128(x, h, p);
+        }
+        check_subgroup_size(&subgroup, n)?;
+        Ok(subgroup)
+    }
+    // (a + bi)(c + di) = (ac - bd) + (ad + bc)i in the gaussian integers modulo p
+    fn gaussian_mul((a, b): (u64, u64), (c, d): (u64, u64), p: u64) -> (u64, u64) {
+        let bd = mod_mul(b, d, p);
+        let real = (mod_mul(a, c, p) + (p - bd)) % p;
+        let imag = ((mod_mul(a, d, p) as u128 + mod_mul(b, c, p) as u128) % p as u128) as u64;
+        (real, imag)
+    }
+    fn gaussian_pow(mut z: (u64, u64), mut e: u128, p: u64) -> (u64, u64) {
+        let mut result = (1, 0);
+        while e > 0 {
+            if e % 2 == 1 {
+                result = gaussian_mul(result, z, p);
+            }
+            z = gaussian_mul(z, z, p);
+            e /= 2;
+        }
+        result
+    }
+    // the subgroup of size n of the units of the gaussian integers Z[i]/p, elements are (real, imag) pairs in power order from (1, 0)
+    // for p = 3 mod 4 the ring is the field with p^2 elements, its units are cyclic of order p^2 - 1 and the subgroup is unique
+    // for p = 1 mod 4 the units are a product of two cyclic groups of order p-1, an element of order n exists when n divides p-1
+    // but there are several subgroups of that size and a random one is returned
+    // p = 2 has the units 1 and i
+    pub fn multiplicative_subgroup_gaussian(
+        p: u64,
+        n: u64,
+    ) -> Result<Vec<(u64, u64)>, SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        // the largest order of a unit
+        let exponent = match p % 4 {
+            3 => p as u128 * p as u128 - 1,
+            1 => p as u128 - 1,
+            _ => 2,
+        };
+        if n == 0 || exponent % n as u128 != 0 {
+            return Err(SubgroupError::NotFactor);
+        }
+        let order_factors = prime_factors(n);
+        let random = rng_available();
+        let mut next = 0;
+        let h = loop {
+            // random pairs, or every pair in turn when there is no random number generator
+            let z = if random {
+                let mut rng = rand::thread_rng();
+                (rng.gen_range(0..p), rng.gen_range(0..p))
+            } else {
+                next += 1;
+                (next % p, next / p % p)
+            };
+            // z is a unit when its norm a^2 + b^2 is not 0 modulo p
+            let norm = (mod_mul(z.0, z.0, p) as u128 + mod_mul(z.1, z.1, p) as u128) % p as u128;
+            if norm == 0 {
+                continue;
+            }
+            let h = gaussian_pow(z, exponent / n as u128, p);
+            if order_factors
+                .iter()
+                .all(|&q| gaussian_pow(h, (n / q) as u128, p) != (1, 0))
+            {
+                break h;
+            }
+        };
+        let mut subgroup = Vec::with_capacity(n as usize);
+        let mut z = (1, 0);
+        for _ in 0..n {
+            subgroup.push(z);
+            z = gaussian_mul(z, h, p);
+        }
+        Ok(subgroup)
+    }
+}
+// this mod is where i put error
+mod error {
+    // NotFactorError and NotPrimeError are no longer returned, every boxed entry point reports a SubgroupError
+    // they are kept so code that names them still builds
+    // custom error if n is not a factor of p-1
+    #[derive(Debug)]
+    pub struct NotFactorError;
+    impl std::error::Error for NotFactorError {
+        fn description(&self) -> &str {
+            "n is not a factor of p-1"
+        }
+    }
+    impl std::fmt::Display for NotFactorError {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "n is not a factor of p-1")
+        }
+    }
+    //custom error if p is not prime
+    #[derive(Debug)]
+    pub struct NotPrimeError;
+    impl std::error::Error for NotPrimeError {
+        fn description(&self) -> &str {
+            "p is not prime"
+        }
+    }
+    impl std::fmt::Display for NotPrimeError {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "p is not prime")
+        }
+    }
+    // one error type for everything, so callers can match on what went wrong instead of downcasting
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SubgroupError {
+        // p is not prime
+        NotPrime,
+        // n is not a factor of p-1
+        NotFactor,
+        // the element is 0 modulo p so it is not in the multiplicative group
+        ZeroElement,
+        // a bit length that is too small to hold a prime or too large for u64
+        BitLengthUnsupported,
+        // the size has to be a power of two
+        NotPowerOfTwo,
+        // the number of columns does not divide the number of elements
+        InvalidShape,
+        // the element is not in the subgroup of size n
+        NotInSubgroup,
+        // the supplied prime factors are not the factorization of p-1
+        InvalidFactors,
+        // no primitive root turned up within the allowed number of random attempts
+        GeneratorNotFound,
+        // the data given to a transform plan does not have the length the plan was made for
+        LengthMismatch,
+        // the subgroup has more elements than the limit that was set
+        TooLarge,
+        // n divides p-1 but the generated subgroup does not have n distinct elements, a bug in the generator rather than in the input
+        WrongSize,
+    }
+    impl std::error::Error for SubgroupError {}
+    // error when a "p,n" request string cannot be parsed
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ParseRequestError {
+        // there is no comma between p and n
+        MissingComma,
+        // one of the two parts is not a number
+        InvalidNumber,
+        // one of the two parts does not fit in u64
+        Overflow,
+    }
+    impl std::error::Error for ParseRequestError {}
+    impl std::fmt::Display for ParseRequestError {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match self {
+                ParseRequestError::MissingComma => {
+                    write!(f, "expected p and n separated by a comma")
+                }
+                ParseRequestError::InvalidNumber => write!(f, "p and n have to be numbers"),
+                ParseRequestError::Overflow => write!(f, "number is too large"),
+            }
+        }
+    }
+    impl std::fmt::Display for SubgroupError {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match self {
+                SubgroupError::NotPrime => write!(f, "p is not prime"),
+                SubgroupError::NotFactor => write!(f, "n is not a factor of p-1"),
+                SubgroupError::ZeroElement => write!(f, "element is 0 modulo p"),
+                SubgroupError::BitLengthUnsupported => write!(f, "bit length is not supported"),
+                SubgroupError::NotPowerOfTwo => write!(f, "n is not a power of two"),
+                SubgroupError::InvalidShape => write!(f, "cols does not divide n"),
+                SubgroupError::NotInSubgroup => write!(f, "element is not in the subgroup"),
+                SubgroupError::InvalidFactors => {
+                    write!(f, "the factors are not the prime factors of p-1")
+                }
+                SubgroupError::GeneratorNotFound => {
+                    write!(f, "no primitive root found within the attempt limit")
+                }
+                SubgroupError::LengthMismatch => {
+                    write!(f, "data length does not match the transform size")
+                }
+                SubgroupError::TooLarge => write!(f, "the subgroup is larger than the size limit"),
+                SubgroupError::WrongSize => {
+                    write!(f, "the generated subgroup does not have n elements")
+                }
+            }
+        }
+    }
+}
+mod field {
+    use crate::error::SubgroupError;
+    use crate::primitive_root::{
+        carmichael_lambda, euler_totient, factors, mobius, prime_factorization, prime_factors,
+    };
+    use crate::sanity_checks::{gcd, is_prime_deterministic, mod_exp, mod_mul, rng_available};
+    use rand::Rng;
+    use std::collections::BTreeMap;
+    use std::ops::{Add, Mul};
+
+    // an element of the field modulo P where P is fixed at compile time
+    // P is never checked, it is up to the caller to only use a prime P
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Fp<const P: u64>(u64);
+    impl<const P: u64> Fp<P> {
+        pub fn new(x: u64) -> Self {
+            Fp(x % P)
+        }
+        pub fn value(self) -> u64 {
+            self.0
+        }
+        pub fn pow(self, e: u64) -> Self {
+            Fp(mod_exp(self.0, e, P))
+        }
+        // the inverse by fermat's little theorem x^(P-2), zero has no inverse
+        pub fn inverse(self) -> Option<Self> {
+            if self.0 == 0 {
+                return None;
+            }
+            Some(self.pow(P - 2))
+        }
+    }
+    impl<const P: u64> Add for Fp<P> {
+        type Output = Self;
+        fn add(self, other: Self) -> Self {
+            Fp(((self.0 as u128 + other.0 as u128) % P as u128) as u64)
+        }
+    }
+    impl<const P: u64> Mul for Fp<P> {
+        type Output = Self;
+        fn mul(self, other: Self) -> Self {
+            Fp(mod_mul(self.0, other.0, P))
+        }
+    }
+    // generate a random element from a field of modulo p the random element will be tested to be a valid primimitive root
+    // to get a valid candidate the elements are in the range of 2 <= x <= p-2
+    // that range is empty for p <= 3 so p-1 is returned instead, the only primitive root of 2 and 3
+    // this needs a seeded random number generator, check rng_available before looping on it
+    pub fn generate_candidate(p: u64) -> u64 {
+        if p <= 3 {
+            return p.saturating_sub(1);
+        }
+        let mut rng = rand::thread_rng();
+        rng.gen_range(2..p - 1)
+    }
+    // check if a number is a primitive root modulo p
+    // kept for older callers, the name suggests it works for any group but p has to be prime
+    #[deprecated(note = "use is_primitive_root, or is_primitive_root_mod for composite moduli")]
+    pub fn is_generator(p: u64, g: u64) -> bool {
+        is_primitive_root(p, g)
+    }
+    // check if g is a primitive root modulo the prime p
+    // the order of g divides p-1, so if it is smaller than p-1 it divides (p-1)/q for some prime q dividing p-1
+    // that means only the distinct prime factors of p-1 need to be checked instead of every divisor
+    // if g^((p-1)/q) mod p == 1 for any prime factor q of p-1 then g is not a primitive root modulo p
+    pub fn is_primitive_root(p: u64, g: u64) -> bool {
+        is_primitive_root_with_factors(p, g, &prime_factors(p.saturating_sub(1)))
+    }
+    // same as is_primitive_root with the distinct prime factors of p-1 given by the caller instead of factoring p-1
+    // the answer is only right if factors are exactly those primes, a 0 among them gives false
+    // a g sharing a factor with p is never 1 modulo p whatever the power, so it is turned away up front
+    pub fn is_primitive_root_with_factors(p: u64, g: u64, factors: &[u64]) -> bool {
+        if p < 2 || gcd(g, p) != 1 || factors.contains(&0) {
+            return false;
+        }
+        for &q in factors {
+            if mod_exp(g, (p - 1) / q, p) == 1 {
+                return false;
             }
         }
         true
     }
-}
+    // the smallest primitive root modulo the prime p, found by scanning upwards from 2
+    // the caller has to make sure p is prime, None is only returned when nothing was found
+    pub fn smallest_primitive_root(p: u64) -> Option<u64> {
+        if p == 2 {
+            return Some(1);
+        }
+        let factors = prime_factors(p.saturating_sub(1));
+        (2..p).find(|&g| is_primitive_root_with_factors(p, g, &factors))
+    }
+    // a primitive root modulo any n that has one, that is 1, 2, 4, q^k and 2q^k for an odd prime q, None for the others
+    // for q^k the smallest primitive root g modulo q is used, or g + q in the rare case g^(q-1) = 1 modulo q^2,
+    // one that works modulo q^2 works for every higher power. for 2q^k the odd one of g and g + q^k is taken,
+    // as the group modulo 2q^k is the same as the one modulo q^k restricted to odd numbers
+    // n = 1 has the single residue 0
+    pub fn primitive_root_mod(n: u64) -> Option<u64> {
+        match n {
+            0 => return None,
+            1 => return Some(0),
+            2 => return Some(1),
+            4 => return Some(3),
+            _ => {}
+        }
+        let odd = if n % 2 == 0 { n / 2 } else { n };
+        let factorization = prime_factorization(odd);
+        let &[(q, k)] = factorization.as_slice() else {
+            return None;
+        };
+        if q == 2 {
+            return None;
+        }
+        let mut g = smallest_primitive_root(q)?;
+        // q^2 divides n here so it fits in u64
+        if k >= 2 && mod_exp(g, q - 1, q * q) == 1 {
+            g += q;
+        }
+        if n % 2 == 0 && g % 2 == 0 {
+            g += odd;
+        }
+        Some(g)
+    }
+    // the k smallest primitive roots modulo the prime p in ascending order, fewer if p does not have k of them
+    // handy when a test needs several independent generators
+    pub fn primitive_roots_take(p: u64, k: usize) -> Result<Vec<u64>, SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        let factors = prime_factors(p - 1);
+        Ok((1..p)
+            .filter(|&g| is_primitive_root_with_factors(p, g, &factors))
+            .take(k)
+            .collect())
+    }
+    // find a random primitive root modulo the prime p
+    pub fn find_primitive_root(p: u64) -> Result<u64, SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        random_primitive_root(p)
+    }
+    // the random search behind find_primitive_root, p is trusted to be prime
+    // a composite p let through by a custom primality test can have no primitive root at all, so after
+    // MAX_ATTEMPTS random candidates every candidate is tried in order and GeneratorNotFound means none worked
+    pub fn random_primitive_root(p: u64) -> Result<u64, SubgroupError> {
+        const MAX_ATTEMPTS: u32 = 1_000;
+        if p < 2 {
+            return Err(SubgroupError::NotPrime);
+        }
+        // there are no candidates between 2 and p-2 for these two
+        if p <= 3 {
+            return Ok(p - 1);
+        }
+        if rng_available() {
+            let factors = prime_factors(p - 1);
+            if let Some(g) = (0..MAX_ATTEMPTS)
+                .map(|_| generate_candidate(p))
+                .find(|&g| is_primitive_root_with_factors(p, g, &factors))
+            {
+                return Ok(g);
+            }
+        }
+        smallest_primitive_root(p).ok_or(SubgroupError::GeneratorNotFound)
+    }
+    // the number of primitive roots modulo the prime p, phi(p-1)
+    pub fn count_primitive_roots(p: u64) -> u64 {
+        if p < 2 {
+            return 0;
+        }
+        euler_totient(p - 1)
+    }
+    // the primes p <= limit that have g as a primitive root, in ascending order
+    // the primes come from a sieve of eratosthenes so limit has to fit the memory of one byte per number
+    // a limit with no room for the sieve in usize is TooLarge
+    pub fn primes_with_primitive_root(g: u64, limit: u64) -> Result<Vec<u64>, SubgroupError> {
+        let limit = usize::try_from(limit)
+            .ok()
+            .filter(|&limit| limit < usize::MAX)
+            .ok_or(SubgroupError::TooLarge)?;
+        let mut composite = vec![false; limit + 1];
+        let mut primes = Vec::new();
+        for i in 2..=limit {
+            if composite[i] {
+                continue;
+            }
+            primes.push(i as u64);
+            for multiple in (i.saturating_mul(i)..=limit).step_by(i) {
+                composite[multiple] = true;
+            }
+        }
+        Ok(primes
+            .into_iter()
+            .filter(|&p| is_primitive_root(p, g))
+            .collect())
+    }
+    // the smallest prime p <= limit whose smallest primitive root is at least min_root, None if there is none
+    // the least primitive root grows very slowly, the records are 3 at 7, 5 at 23, 6 at 41, 7 at 71 and 19 at 191
+    pub fn prime_with_large_least_root(min_root: u64, limit: u64) -> Option<u64> {
+        (2..=limit)
+            .filter(|&p| is_prime_deterministic(p))
+            .find(|&p| smallest_primitive_root(p).is_some_and(|g| g >= min_root))
+    }
+    // the fraction of the primes in [from, to] that have base as a primitive root, 0 when there are no primes in the range
+    // primes dividing base count as primes where it is not a primitive root
+    // for base 2 this tends to artin's constant 0.3739... as the range grows
+    pub fn primitive_root_density(base: u64, from: u64, to: u64) -> f64 {
+        let mut primes = 0;
+        let mut roots = 0;
+        for p in (from..=to).filter(|&p| is_prime_deterministic(p)) {
+            primes += 1;
+            if is_primitive_root(p, base) {
+                roots += 1;
+            }
+        }
+        if primes == 0 {
+            return 0.0;
+        }
+        roots as f64 / primes as f64
+    }
+    // how many elements of the multiplicative group modulo p have each order d, for every divisor d of p-1
+    // a cyclic group of size p-1 has exactly phi(d) elements of order d so nothing is enumerated
+    pub fn order_histogram(p: u64) -> Result<BTreeMap<u64, u64>, SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        Ok(factors(p - 1)
+            .into_iter()
+            .map(|d| (d, euler_totient(d)))
+            .collect())
+    }
+    // lazily go through the primitive roots modulo p
+    // once one primitive root g is known the others are exactly g^k for k coprime to p-1
+    // they are yielded in ascending order of k, not in ascending order of value
+    pub fn primitive_roots_iter(p: u64) -> Result<impl Iterator<Item = u64>, SubgroupError> {
+        let g = find_primitive_root(p)?;
+        Ok((1..=p - 1)
+            .filter(move |&k| gcd(k, p - 1) == 1)
+            .map(move |k| mod_exp(g, k, p)))
+    }
+    // check if g is a primitive root modulo any n, prime or not
+    // g has to be coprime to n and its order has to be phi(n), since every order divides carmichael_lambda(n)
+    // this needs lambda(n) == phi(n) and g^(lambda/q) != 1 for every prime q dividing lambda
+    pub fn is_primitive_root_mod(g: u64, n: u64) -> bool {
+        if n == 0 || gcd(g, n) != 1 {
+            return false;
+        }
+        let lambda = carmichael_lambda(n);
+        if lambda != euler_totient(n) {
+            return false;
+        }
+        for q in prime_factors(lambda) {
+            if mod_exp(g, lambda / q, n) == 1 {
+                return false;
+            }
+        }
+        true
+    }
+    // whether the multiplicative group modulo modulus has exactly one subgroup of size n
+    // always true for a cyclic group, otherwise it depends on the part of the group for each prime q dividing n:
+    // the choice of subgroup is forced when that part is cyclic (q divides lambda as often as phi) or when n takes all of it
+    // n has to divide phi(modulus), there is no subgroup of that size otherwise
+    pub fn unique_subgroup_of_order(modulus: u64, n: u64) -> Result<bool, SubgroupError> {
+        let phi = euler_totient(modulus);
+        if modulus == 0 || n == 0 || phi % n != 0 {
+            return Err(SubgroupError::NotFactor);
+        }
+        let lambda = carmichael_lambda(modulus);
+        let valuation = |mut x: u64, q: u64| {
+            let mut v = 0;
+            while x % q == 0 {
+                x /= q;
+                v += 1;
+            }
+            v
+        };
+        Ok(prime_factors(n).into_iter().all(|q| {
+            valuation(lambda, q) == valuation(phi, q) || valuation(n, q) == valuation(phi, q)
+        }))
+    }
+    // the coefficients modulo p of the n-th cyclotomic polynomial, the constant term first, of degree phi(n)
+    // its roots modulo p are exactly the primitive n-th roots of unity when n divides p-1
+    // it is the product of (x^d - 1)^mobius(n/d) over the divisors d of n, all the multiplications are done before
+    // the divisions so every division by x^d - 1 is exact
+    pub fn cyclotomic_mod(n: u64, p: u64) -> Result<Vec<u64>, SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        // there is no 0-th cyclotomic polynomial
+        if n == 0 {
+            return Err(SubgroupError::NotFactor);
+        }
+        let divisors = factors(n);
+        let mut poly = vec![1];
+        for &d in divisors.iter().filter(|&&d| mobius(n / d) == 1) {
+            // multiply by x^d - 1
+            let d = d as usize;
+            let mut product = vec![0; poly.len() + d];
+            for (i, &c) in poly.iter().enumerate() {
+                product[i + d] = add_mod(product[i + d], c, p);
+                product[i] = add_mod(product[i], p - c, p);
+            }
+            poly = product;
+        }
+        for &d in divisors.iter().filter(|&&d| mobius(n / d) == -1) {
+            // divide by x^d - 1 from the top, x^i = x^(i-d) * (x^d - 1) + x^(i-d)
+            let d = d as usize;
+            let mut quotient = vec![0; poly.len() - d];
+            for i in (d..poly.len()).rev() {
+                quotient[i - d] = poly[i];
+                poly[i - d] = add_mod(poly[i - d], poly[i], p);
+            }
+            poly = quotient;
+        }
+        Ok(poly)
+    }
+    // a + b modulo p for a, b <= p, in u128 as the sum does not fit in u64 once p is above 2^63
+    fn add_mod(a: u64, b: u64, p: u64) -> u64 {
+        ((a as u128 + b as u128) % p as u128) as u64
+    }
+}
+// helpers for number theoretic transforms over the subgroups
+mod ntt {
+    use crate::error::SubgroupError;
+    use crate::field::smallest_primitive_root;
+    use crate::multiplicative_subgruop::{cofactor, powers, projection_exponent};
+    use crate::primitive_root::prime_factors;
+    use crate::sanity_checks::{is_prime_deterministic, mod_exp, mod_mul};
+
+    // check that n is a power of two as the radix 2 transforms need, and return log2(n)
+    pub fn require_power_of_two(n: u64) -> Result<u32, SubgroupError> {
+        if n.is_power_of_two() {
+            Ok(n.trailing_zeros())
+        } else {
+            Err(SubgroupError::NotPowerOfTwo)
+        }
+    }
+    // check that p admits a radix 2 transform of size n, reporting the first thing that fails:
+    // p has to be prime, n a power of two and n has to divide p-1
+    // every transform and table in this module calls it before doing any work
+    pub fn supports_transform(p: u64, n: u64) -> Result<(), SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        require_power_of_two(n)?;
+        cofactor(p, n).map(|_| ())
+    }
+    // reorder data so the element at index i moves to the index with the bits of i reversed, the input order of an in place radix 2 transform
+    // the length has to be a power of two
+    pub fn bit_reverse_permute<T>(data: &mut [T]) -> Result<(), SubgroupError> {
+        let bits = require_power_of_two(data.len() as u64)?;
+        if bits == 0 {
+            return Ok(());
+        }
+        for i in 0..data.len() {
+            let j = i.reverse_bits() >> (usize::BITS - bits);
+            // every pair is swapped once, from its smaller index
+            if i < j {
+                data.swap(i, j);
+            }
+        }
+        Ok(())
+    }
+    // the primitive radix-th root h^(n/radix) where h generates the subgroup of size n, used by the radix steps of a mixed radix transform
+    // h comes from the smallest primitive root so the roots of different strides agree with each other
+    pub fn stride_root(p: u64, n: u64, radix: u64) -> Result<u64, SubgroupError> {
+        let e = projection_exponent(p, n)?;
+        if radix == 0 || n % radix != 0 {
+            return Err(SubgroupError::NotFactor);
+        }
+        let g = smallest_primitive_root(p).ok_or(SubgroupError::NotPrime)?;
+        let h = mod_exp(g, e, p);
+        Ok(mod_exp(h, n / radix, p))
+    }
+    // the number of factors of two in n, a prime p supports radix 2 transforms up to size 2^two_adicity(p - 1)
+    // 0 has no odd part, it is given 64 like trailing_zeros does
+    pub fn two_adicity(n: u64) -> u32 {
+        n.trailing_zeros()
+    }
+    // how well suited a prime is for transforms, computed by transform_prime_quality
+    // two_adicity bounds the radix 2 sizes, largest_odd_factor is the largest prime factor of the odd part of p-1
+    // (1 when p-1 is a power of two) and the smaller it is the more mixed radix sizes p supports
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct PrimeQuality {
+        pub two_adicity: u32,
+        pub largest_odd_factor: u64,
+        pub bits: u32,
+    }
+    pub fn transform_prime_quality(p: u64) -> Result<PrimeQuality, SubgroupError> {
+        if !is_prime_deterministic(p) {
+            return Err(SubgroupError::NotPrime);
+        }
+        let two_adicity = two_adicity(p - 1);
+        let largest_odd_factor = prime_factors((p - 1) >> two_adicity)
+            .last()
+            .copied()
+            .unwrap_or(1);
+        Ok(PrimeQuality {
+            two_adicity,
+            largest_odd_factor,
+            bits: u64::BITS - p.leading_zeros(),
+        })
+    }
+    // the prime closest to target with 2^subgroup_log2 dividing p-1, looking above and below at the same time
+    // on a tie the smaller one is returned, None if neither direction has one in u64
+    pub fn nearest_ntt_prime(target: u64, subgroup_log2: u32) -> Option<u64> {
+        let step = 1u64.checked_shl(subgroup_log2)?;
+        // the candidates are the numbers k * step + 1, below runs down from the last one <= target
+        // and above up from the first one > target
+        let mut below = target.checked_sub(1).map(|t| t / step * step + 1);
+        let mut above = match below {
+            Some(b) => b.checked_add(step),
+            None => Some(1),
+        };
+        loop {
+            let take_below = match (below, above) {
+                (None, None) => return None,
+                (Some(b), Some(a)) => target - b <= a - target,
+                (b, _) => b.is_some(),
+            };
+            if take_below {
+                let b = below.unwrap();
+                if is_prime_deterministic(b) {
+                    return Some(b);
+                }
+                below = b.checked_sub(step).filter(|&b| b >= 2);
+            } else {
+                let a = above.unwrap();
+                if is_prime_deterministic(a) {
+                    return Some(a);
+                }
+                above = a.checked_add(step);
+            }
+        }
+    }
+    // the smallest prime p >= min with at least two_adicity factors of two in p-1
+    // None if the search runs past u64
+    pub fn find_transform_prime(min: u64, two_adicity: u32) -> Option<u64> {
+        let step = 1u64.checked_shl(two_adicity)?;
+        // the first k with k * step + 1 >= min
+        let mut k = min.saturating_sub(1).div_ceil(step);
+        loop {
+            let p = k.checked_mul(step)?.checked_add(1)?;
+            if is_prime_deterministic(p) {
+                return Some(p);
+            }
+            k += 1;
+        }
+    }
+    // the n powers w^0 .. w^(n-1) of the primitive n-th root w = stride_root(p, n, n), with w^i stored at the bit reversal of i
+    // the order in place transforms read their twiddles in, so they need no permutation of their own
+    pub fn twiddles_bit_reversed(p: u64, n: u64) -> Result<Vec<u64>, SubgroupError> {
+        supports_transform(p, n)?;
+        let mut twiddles = powers(stride_root(p, n, n)?, n, p);
+        bit_reverse_permute(&mut twiddles)?;
+        Ok(twiddles)
+    }
+    // everything a transform of size n modulo p needs, computed by ntt_roots
+    // forward holds w^0 .. w^(n-1) for w = stride_root(p, n, n) and inverse the same powers of w^-1, both in
+    // the bit reversed order of twiddles_bit_reversed so one butterfly loop serves both directions
+    // inverse[i] is the inverse of forward[i] and n_inverse scales the inverse transform
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct NttRoots {
+        pub forward: Vec<u64>,
+        pub inverse: Vec<u64>,
+        pub n_inverse: u64,
+    }
+    pub fn ntt_roots(p: u64, n: u64) -> Result<NttRoots, SubgroupError> {
+        supports_transform(p, n)?;
+        let root = stride_root(p, n, n)?;
+        // w^(n-1) = w^-1, as w has order n
+        let inv_root = mod_exp(root, n - 1, p);
+        let mut forward = powers(root, n, p);
+        let mut inverse = powers(inv_root, n, p);
+        bit_reverse_permute(&mut forward)?;
+        bit_reverse_permute(&mut inverse)?;
+        Ok(NttRoots {
+            forward,
+            inverse,
+            n_inverse: mod_exp(n % p, p - 2, p),
+        })
+    }
+    fn add(a: u64, b: u64, p: u64) -> u64 {
+        ((a as u128 + b as u128) % p as u128) as u64
+    }
+    // a - b for a and b already reduced modulo p
+    fn sub(a: u64, b: u64, p: u64) -> u64 {
+        add(a, p - b, p)
+    }
+    // the number theoretic transform of data in place, X_k = sum of a_j * w^(jk) with w = stride_root(p, n, n)
+    // the length n has to be a power of two dividing p-1, the output is in natural order
+    // builds an NttPlan each call, keep a plan around when transforming many times at the same size
+    pub fn ntt(data: &mut [u64], p: u64) -> Result<(), SubgroupError> {
+        NttPlan::new(p, data.len() as u64)?.forward(data)
+    }
+    // the inverse of ntt, the same transform with w^-1 followed by dividing by n
+    pub fn intt(data: &mut [u64], p: u64) -> Result<(), SubgroupError> {
+        NttPlan::new(p, data.len() as u64)?.inverse(data)
+    }
+    // the same transform as ntt without a twiddle table, each stage steps its root through the powers by multiplying
+    // so only O(1) memory is used besides data, at the cost of one extra multiplication per butterfly
+    pub fn ntt_low_memory(data: &mut [u64], p: u64) -> Result<(), SubgroupError> {
+        let n = data.len() as u64;
+        supports_transform(p, n)?;
+        for x in data.iter_mut() {
+            *x %= p;
+        }
+        bit_reverse_permute(data)?;
+        if n <= 1 {
+            return Ok(());
+        }
+        let root = stride_root(p, n, n)?;
+        let mut len = 2;
+        while len <= data.len() {
+            // the primitive len-th root, the same twiddles ntt reads at every (n/len)-th entry of its table
+            let step = mod_exp(root, n / len as u64, p);
+            for block in data.chunks_exact_mut(len) {
+                let (low, high) = block.split_at_mut(len / 2);
+                let mut w = 1;
+                for (u, v) in low.iter_mut().zip(high.iter_mut()) {
+                    let t = mod_mul(*v, w, p);
+                    (*u, *v) = (add(*u, t, p), sub(*u, t, p));
+                    w = mod_mul(w, step, p);
+                }
+            }
+            len *= 2;
+        }
+        Ok(())
+    }
+    // the twiddle factors for transforms of size n modulo p, computed once and reused by every forward and inverse call
+    // twiddles holds w^0 .. w^(n/2 - 1) and inv_twiddles the same powers of w^-1, a stage with blocks of size len
+    // uses every (n/len)-th entry
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct NttPlan {
+        p: u64,
+        n: u64,
+        twiddles: Vec<u64>,
+        inv_twiddles: Vec<u64>,
+        n_inverse: u64,
+    }
+    impl NttPlan {
+        pub fn new(p: u64, n: u64) -> Result<Self, SubgroupError> {
+            supports_transform(p, n)?;
+            // sizes 1 and 2 only need the twiddle 1, so no root is looked up for them
+            let (twiddles, inv_twiddles) = if n <= 2 {
+                (vec![1; n as usize / 2], vec![1; n as usize / 2])
+            } else {
+                let root = stride_root(p, n, n)?;
+                let inv_root = mod_exp(root, n - 1, p);
+                (powers(root, n / 2, p), powers(inv_root, n / 2, p))
+            };
+            Ok(NttPlan {
+                p,
+                n,
+                twiddles,
+                inv_twiddles,
+                n_inverse: mod_exp(n % p, p - 2, p),
+            })
+        }
+        pub fn p(&self) -> u64 {
+            self.p
+        }
+        pub fn n(&self) -> u64 {
+            self.n
+        }
+        pub fn forward(&self, data: &mut [u64]) -> Result<(), SubgroupError> {
+            self.run(data, &self.twiddles)
+        }
+        pub fn inverse(&self, data: &mut [u64]) -> Result<(), SubgroupError> {
+            self.run(data, &self.inv_twiddles)?;
+            for x in data.iter_mut() {
+                *x = mod_mul(*x, self.n_inverse, self.p);
+            }
+            Ok(())
+        }
+        fn run(&self, data: &mut [u64], twiddles: &[u64]) -> Result<(), SubgroupError> {
+            let p = self.p;
+            if data.len() as u64 != self.n {
+                return Err(SubgroupError::LengthMismatch);
+            }
+            for x in data.iter_mut() {
+                *x %= p;
+            }
+            bit_reverse_permute(data)?;
+            let mut len = 2;
+            while len <= data.len() {
+                let stride = data.len() / len;
+                for block in data.chunks_exact_mut(len) {
+                    let (low, high) = block.split_at_mut(len / 2);
+                    for (j, (u, v)) in low.iter_mut().zip(high.iter_mut()).enumerate() {
+                        let t = mod_mul(*v, twiddles[j * stride], p);
+                        (*u, *v) = (add(*u, t, p), sub(*u, t, p));
+                    }
+                }
+                len *= 2;
+            }
+            Ok(())
+        }
+    }
+}
+// parsing of subgroup requests written as "p,n", kept apart from the computation
+mod request {
+    use crate::error::ParseRequestError;
+    use std::num::IntErrorKind;
+    use std::str::FromStr;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SubgroupRequest {
+        pub prime: u64,
+        pub order: u64,
+    }
+    // decimal or 0x prefixed hexadecimal, underscores between digits are ignored like in rust literals
+    // only digits and single underscores between them are accepted, no sign and no empty groups
+    fn parse_number(s: &str) -> Result<u64, ParseRequestError> {
+        let s = s.trim();
+        let (digits, radix) = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(hex) => (hex, 16),
+            None => (s, 10),
+        };
+        // from_str_radix alone would accept a leading +, so every group between underscores is checked first
+        let valid = digits
+            .split('_')
+            .all(|group| !group.is_empty() && group.chars().all(|c| c.is_digit(radix)));
+        if !valid {
+            return Err(ParseRequestError::InvalidNumber);
+        }
+        let digits = digits.replace('_', "");
+        u64::from_str_radix(&digits, radix).map_err(|e| match e.kind() {
+            IntErrorKind::PosOverflow => ParseRequestError::Overflow,
+            _ => ParseRequestError::InvalidNumber,
+        })
+    }
+    // parses "7,3" into SubgroupRequest { prime: 7, order: 3 }, spaces around the numbers are allowed
+    // and so are hex and underscores, "0xFFFF_FFFB, 1_000"
+    impl FromStr for SubgroupRequest {
+        type Err = ParseRequestError;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let (prime, order) = s.split_once(',').ok_or(ParseRequestError::MissingComma)?;
+            Ok(SubgroupRequest {
+                prime: parse_number(prime)?,
+                order: parse_number(order)?,
+            })
+        }
+    }
+}
+
+fn main() {
+    println!("Hello, world!");
+}
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use crate::multiplicative_subgruop::{
+        multiplicative_subgroup, multiplicative_subgroup_deterministic,
+        multiplicative_subgroup_result, multiplicative_subgroup_u128,
+        multiplicative_subgroup_with_stats, subgroup_pow, subgroups_equal, SubgroupResult,
+    };
+    use crate::primitive_root::{factors, factors_into};
+    use crate::sanity_checks::{is_prime_u128, mod_exp, mod_exp_u128};
+    use field::{is_primitive_root, is_primitive_root_mod};
+
+    #[test]
+    fn test_factors() {
+        assert_eq!(factors(12), vec![1, 2, 3, 4, 6, 12]);
+        assert_eq!(factors(15), vec![1, 3, 5, 15]);
+        assert_eq!(factors(17), vec![1, 17]);
+        assert_eq!(factors(24), vec![1, 2, 3, 4, 6, 8, 12, 24]);
+        assert_eq!(factors(6), vec![1, 2, 3, 6]);
+    }
+    // factors_into should fill the buffer with the same thing factors returns, even when reused
+    #[test]
+    fn test_factors_into() {
+        let mut buf = Vec::new();
+        for k in [1, 6, 12, 17, 24, 36] {
+            factors_into(k, &mut buf);
+            assert_eq!(buf, factors(k));
+        }
+    }
+    // test is_generator function
+    #[test]
+    #[allow(deprecated)]
+    fn test_is_generator() {
+        use field::is_generator;
+        assert_eq!(is_generator(7, 3), true);
+        assert_eq!(is_generator(11, 2), true);
+        assert_eq!(is_generator(13, 2), true);
+        assert_eq!(is_generator(17, 3), true);
+        assert_eq!(is_generator(19, 2), true);
+        assert_eq!(is_generator(23, 5), true);
+        assert_eq!(is_generator(29, 2), true);
+        assert_eq!(is_generator(31, 3), true);
+        assert_eq!(is_generator(37, 2), true);
+        assert_eq!(is_generator(41, 6), true);
+        assert_eq!(is_generator(43, 3), true);
+        assert_eq!(is_generator(47, 5), true);
+        assert_eq!(is_generator(53, 2), true);
+        assert_eq!(is_generator(59, 2), true);
+        assert_eq!(is_generator(61, 2), true);
+        assert_eq!(is_generator(67, 2), true);
+        assert_eq!(is_generator(71, 7), true);
+        assert_eq!(is_generator(73, 5), true);
+        assert_eq!(is_generator(79, 3), true);
+        assert_eq!(is_generator(83, 2), true);
+        assert_eq!(is_generator(89, 3), true);
+        assert_eq!(is_generator(97, 5), true);
+        assert_eq!(is_generator(101, 2), true);
+        assert_eq!(is_generator(103, 5), true);
+        assert_eq!(is_generator(107, 2), true);
+        assert_eq!(is_generator(109, 6), true);
+        assert_eq!(is_generator(113, 3), true);
+        assert_eq!(is_generator(127, 3), true);
+        // 85 is not a primitive root of 337, 85^8 = 1 mod 337
+        assert_eq!(is_generator(337, 85), false);
+        assert_eq!(is_generator(337, 10), true);
+    }
+    // is_primitive_root for primes and is_primitive_root_mod for composite moduli
+    #[test]
+    fn test_is_primitive_root() {
+        assert!(is_primitive_root(7, 3));
+        assert!(is_primitive_root(7, 5));
+        assert!(!is_primitive_root(7, 2));
+        assert!(!is_primitive_root(13, 3));
+        assert!(is_primitive_root_mod(3, 7));
+        assert!(!is_primitive_root_mod(2, 7));
+        // (Z/9Z)* and (Z/10Z)* are cyclic
+        assert!(is_primitive_root_mod(2, 9));
+        assert!(!is_primitive_root_mod(4, 9));
+        assert!(is_primitive_root_mod(3, 10));
+        assert!(is_primitive_root_mod(7, 10));
+        assert!(!is_primitive_root_mod(9, 10));
+        // not coprime to the modulus
+        assert!(!is_primitive_root_mod(6, 9));
+        // (Z/8Z)* and (Z/15Z)* are not cyclic so nothing is a primitive root
+        for g in 1..8 {
+            assert!(!is_primitive_root_mod(g, 8));
+        }
+        for g in 1..15 {
+            assert!(!is_primitive_root_mod(g, 15));
+        }
+    }
+    // test miller rabin working correctly so test is prime
+    #[test]
+    fn test_is_prime() {
+        assert_eq!(crate::sanity_checks::is_prime(7, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(11, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(13, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(17, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(19, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(23, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(29, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(31, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(37, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(41, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(43, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(47, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(53, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(59, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(61, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(67, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(71, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(73, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(79, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(83, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(89, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(97, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(101, 5), true);
+        assert_eq!(crate::sanity_checks::is_prime(103, 5), true);
+    }
+    // test the multiplicative subgroup
+    // asserts that for functions with n not a foctor of p - 1 returns error
+    #[test]
+    fn test_multiplicative_subgroup() {
+        // the order after 1 depends on the random generator so only compare the elements as sets
+        assert!(subgroups_equal(
+            &multiplicative_subgroup(7, 3).unwrap(),
+            &[1, 2, 4],
+            7
+        ));
+        assert!(subgroups_equal(
+            &multiplicative_subgroup(11, 5).unwrap(),
+            &[1, 3, 4, 5, 9],
+            11
+        ));
+    }
+    // the stats should report at least one candidate and the generator that was actually used
+    #[test]
+    fn test_multiplicative_subgroup_with_stats() {
+        let (mut subgroup, stats) = multiplicative_subgroup_with_stats(13, 4).unwrap();
+        assert!(stats.candidates_tried >= 1);
+        assert!(is_primitive_root(13, stats.generator));
+        subgroup.sort();
+        assert_eq!(subgroup, vec![1, 5, 8, 12]);
+    }
+    // mod_exp must not overflow once the modulus is above 2^32
+    // and the u128 functions must handle a prime that is about 100 bits
+    #[test]
+    fn test_u128_moduli() {
+        // 2^61 - 1 is prime so fermat's little theorem holds
+        let m61 = (1u64 << 61) - 1;
+        assert_eq!(mod_exp(3, m61 - 1, m61), 1);
+        assert!(crate::sanity_checks::is_prime_deterministic(m61));
+
+        // 2^89 - 1 is a mersenne prime, 2^89 + 1 is divisible by 3
+        let m89: u128 = (1 << 89) - 1;
+        assert!(is_prime_u128(m89));
+        assert!(!is_prime_u128(m89 + 2));
+        assert!(!is_prime_u128(m61 as u128 * ((1 << 31) - 1)));
+        assert_eq!(mod_exp_u128(5, m89 - 1, m89), 1);
+
+        // 30 divides 2^89 - 2 = 2 * (2^88 - 1) and 2^4 - 1 = 15 divides 2^88 - 1
+        let subgroup = multiplicative_subgroup_u128(m89, 30).unwrap();
+        assert_eq!(subgroup.len(), 30);
+        assert_eq!(subgroup[0], 1);
+        let distinct: std::collections::HashSet<_> = subgroup.iter().collect();
+        assert_eq!(distinct.len(), 30);
+        for &x in &subgroup {
+            assert_eq!(mod_exp_u128(x, 30, m89), 1);
+        }
+        assert!(multiplicative_subgroup_u128(m89, 7).is_err());
+        assert!(multiplicative_subgroup_u128(m89 + 2, 2).is_err());
+    }
+    // 5 generates the subgroup of size 4 modulo 13, check the value and the index that subgroup_pow reports
+    #[test]
+    fn test_subgroup_pow() {
+        assert_eq!(subgroup_pow(5, 3, 7, 13, 4), Ok((5, 1)));
+        assert_eq!(subgroup_pow(5, 2, 2, 13, 4), Ok((1, 0)));
+        for i in 0..4 {
+            for exp in [0, 1, 5, 1000, u64::MAX] {
+                let (value, index) = subgroup_pow(5, i, exp, 13, 4).unwrap();
+                assert_eq!(value, mod_exp(mod_exp(5, i, 13), exp, 13));
+                assert_eq!(value, mod_exp(5, index, 13));
+                assert!(index < 4);
+            }
+        }
+    }
+    // arithmetic in Fp with a couple of primes fixed at compile time
+    #[test]
+    fn test_fp() {
+        use field::Fp;
+        assert_eq!(Fp::<97>::new(3).pow(5).value(), 243 % 97);
+        assert_eq!(Fp::<97>::new(200).value(), 6);
+        assert_eq!((Fp::<97>::new(50) + Fp::<97>::new(60)).value(), 13);
+        assert_eq!((Fp::<97>::new(50) * Fp::<97>::new(60)).value(), 3000 % 97);
+        assert_eq!(Fp::<97>::new(0).inverse(), None);
+        for x in 1..97 {
+            let a = Fp::<97>::new(x);
+            assert_eq!(a * a.inverse().unwrap(), Fp::new(1));
+        }
+
+        const M61: u64 = (1 << 61) - 1;
+        let a = Fp::<M61>::new(M61 - 1);
+        assert_eq!((a * a).value(), 1);
+        assert_eq!((a + a).value(), M61 - 2);
+        assert_eq!(a.pow(M61 - 1).value(), 1);
+        assert_eq!(
+            Fp::<M61>::new(12345).inverse().unwrap() * Fp::new(12345),
+            Fp::new(1)
+        );
+    }
+    // (Z/nZ)* is cyclic only for 1, 2, 4, q^k and 2q^k
+    #[test]
+    fn test_is_cyclic_group() {
+        use crate::primitive_root::is_cyclic_group;
+        assert_eq!(is_cyclic_group(15), false);
+        assert_eq!(is_cyclic_group(18), true);
+        assert_eq!(is_cyclic_group(8), false);
+        for n in [1, 2, 3, 4, 7, 9, 25, 27, 50, 98, 121] {
+            assert!(is_cyclic_group(n), "{}", n);
+        }
+        for n in [0, 12, 16, 20, 21, 24, 36, 100] {
+            assert!(!is_cyclic_group(n), "{}", n);
+        }
+        // a cyclic group is exactly one where some element reaches order phi(n)
+        for n in 1..200 {
+            let has_root = (1..=n).any(|g| is_primitive_root_mod(g, n));
+            assert_eq!(is_cyclic_group(n), has_root, "{}", n);
+        }
+    }
+    // the cache agrees with the uncached test and answers repeated queries without recomputing
+    #[test]
+    fn test_prime_cache() {
+        use crate::sanity_checks::{is_prime_deterministic, PrimeCache};
+        let mut cache = PrimeCache::new();
+        for n in 0..500 {
+            assert_eq!(cache.is_prime(n), is_prime_deterministic(n));
+        }
+        assert_eq!(cache.hits(), 0);
+        assert_eq!(cache.len(), 500);
+        assert!(cache.is_prime(97));
+        assert!(!cache.is_prime(91));
+        assert_eq!(cache.hits(), 2);
+        assert_eq!(cache.len(), 500);
+    }
+    // the shared cache gives the same answers from several threads and counts the repeats as hits
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_sync_prime_cache() {
+        use crate::sanity_checks::{is_prime_deterministic, SyncPrimeCache};
+        let cache = SyncPrimeCache::new();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for n in 0..200 {
+                        assert_eq!(cache.is_prime(n), is_prime_deterministic(n));
+                    }
+                });
+            }
+        });
+        assert_eq!(cache.hits(), 600);
+    }
+    // bpsw agrees with the deterministic test and rejects the base 2 strong pseudoprimes
+    #[test]
+    fn test_is_prime_bpsw() {
+        use crate::sanity_checks::{is_prime_bpsw, is_prime_deterministic};
+        let strong_pseudoprimes_base_2 = [
+            2047, 3277, 4033, 4681, 8321, 15841, 29341, 42799, 49141, 52633, 65281, 74665, 80581,
+            85489, 88357, 90751, 104653, 130561, 196093, 220729, 233017, 252601, 253241, 256999,
+            271951, 280601, 314821, 357761, 390937, 458989, 476971, 486737,
+        ];
+        for n in strong_pseudoprimes_base_2 {
+            assert!(!is_prime_bpsw(n), "{}", n);
+        }
+        // strong lucas pseudoprimes are caught by the base 2 test instead
+        for n in [
+            5459, 5777, 10877, 16109, 18971, 22499, 24569, 25199, 40309, 58519,
+        ] {
+            assert!(!is_prime_bpsw(n), "{}", n);
+        }
+        for n in 0..100_000 {
+            assert_eq!(is_prime_bpsw(n), is_prime_deterministic(n), "{}", n);
+        }
+        assert!(is_prime_bpsw((1 << 61) - 1));
+        assert!(is_prime_bpsw(18446744069414584321));
+        assert!(is_prime_bpsw(18446744073709551557));
+        assert!(!is_prime_bpsw(u64::MAX));
+        assert!(!is_prime_bpsw(4294967291 * 4294967279));
+        assert!(!is_prime_bpsw(4294967291 * 4294967291));
+    }
+    // display a subgroup result for logging
+    #[test]
+    fn test_subgroup_result_display() {
+        let result = SubgroupResult {
+            prime: 7,
+            order: 3,
+            generator: 2,
+            elements: vec![1, 2, 4],
+        };
+        assert_eq!(result.to_string(), "⟨2⟩ of order 3 mod 7 = {1, 2, 4}");
+        let trivial = SubgroupResult {
+            prime: 7,
+            order: 1,
+            generator: 1,
+            elements: vec![1],
+        };
+        assert_eq!(trivial.to_string(), "⟨1⟩ of order 1 mod 7 = {1}");
+
+        let result = multiplicative_subgroup_result(13, 4).unwrap();
+        assert!(result.generator == 5 || result.generator == 8);
+        assert!(result
+            .to_string()
+            .starts_with(&format!("⟨{}⟩ of order 4 mod 13 = {{1, ", result.generator)));
+    }
+    // the deterministic variant uses the smallest primitive root so repeated calls give identical output
+    #[test]
+    fn test_multiplicative_subgroup_deterministic() {
+        use field::smallest_primitive_root;
+        assert_eq!(smallest_primitive_root(2), Some(1));
+        assert_eq!(smallest_primitive_root(7), Some(3));
+        assert_eq!(smallest_primitive_root(41), Some(6));
+        assert_eq!(smallest_primitive_root(191), Some(19));
+
+        let first = multiplicative_subgroup_deterministic(13, 4).unwrap();
+        let second = multiplicative_subgroup_deterministic(13, 4).unwrap();
+        assert_eq!(first, second);
+        // 2 is the smallest primitive root modulo 13 so h = 2^3 = 8
+        assert_eq!(first, vec![1, 8, 12, 5]);
+        assert_eq!(
+            multiplicative_subgroup_deterministic(7, 3).unwrap(),
+            vec![1, 2, 4]
+        );
+        assert!(multiplicative_subgroup_deterministic(12, 2).is_err());
+        assert!(multiplicative_subgroup_deterministic(13, 5).is_err());
+    }
+    // projecting any nonzero element lands in the subgroup
+    #[test]
+    fn test_project_into_subgroup() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::{is_in_subgroup, project_into_subgroup};
+        for n in [1, 2, 3, 4, 6, 8, 12, 24] {
+            for a in 1..73 {
+                let x = project_into_subgroup(a, 73, n).unwrap();
+                assert!(is_in_subgroup(x, 73, n), "{} {}", a, n);
+            }
+        }
+        assert!(!is_in_subgroup(3, 7, 3));
+        assert!(!is_in_subgroup(0, 7, 3));
+        assert_eq!(
+            project_into_subgroup(0, 73, 8),
+            Err(SubgroupError::ZeroElement)
+        );
+        assert_eq!(
+            project_into_subgroup(146, 73, 8),
+            Err(SubgroupError::ZeroElement)
+        );
+        assert_eq!(
+            project_into_subgroup(5, 72, 8),
+            Err(SubgroupError::NotPrime)
+        );
+        assert_eq!(
+            project_into_subgroup(5, 73, 5),
+            Err(SubgroupError::NotFactor)
+        );
+        assert_eq!(
+            project_into_subgroup(5, 73, 0),
+            Err(SubgroupError::NotFactor)
+        );
+    }
+    // primes where p-1 has repeated prime factors, checked against the order found by brute force
+    #[test]
+    fn test_is_primitive_root_repeated_factors() {
+        let order = |g: u64, p: u64| (1..p).find(|&d| mod_exp(g, d, p) == 1).unwrap();
+        assert_eq!(order(85, 337), 8);
+        assert!(!is_primitive_root(337, 85));
+        assert!(is_primitive_root(337, 10));
+        assert!(is_primitive_root(409, 21));
+        assert!(is_primitive_root(433, 5));
+        assert!(is_primitive_root(577, 5));
+        assert!(is_primitive_root(1009, 11));
+        assert!(is_primitive_root(7681, 17));
+        assert!(!is_primitive_root(7, 0));
+        assert!(!is_primitive_root(7, 7));
+        assert!(is_primitive_root(2, 1));
+        // 336 = 2^4 * 3 * 7, 432 = 2^4 * 3^3, 576 = 2^6 * 3^2
+        for p in [337, 433, 577] {
+            for g in 1..p {
+                assert_eq!(is_primitive_root(p, g), order(g, p) == p - 1, "{} {}", p, g);
+            }
+        }
+    }
+    // the lazy iterator yields every primitive root exactly once
+    #[test]
+    fn test_primitive_roots_iter() {
+        use field::{count_primitive_roots, primitive_roots_iter};
+        for p in [2, 3, 7, 13, 41, 337] {
+            let count = count_primitive_roots(p);
+            let mut roots: Vec<u64> = primitive_roots_iter(p)
+                .unwrap()
+                .take(count as usize)
+                .collect();
+            assert!(roots.iter().all(|&g| is_primitive_root(p, g)));
+            roots.sort();
+            roots.dedup();
+            assert_eq!(roots.len() as u64, count);
+            let expected: Vec<u64> = (1..p).filter(|&g| is_primitive_root(p, g)).collect();
+            assert_eq!(roots, expected);
+        }
+        assert_eq!(count_primitive_roots(7), 2);
+        assert_eq!(primitive_roots_iter(7).unwrap().count(), 2);
+        assert!(primitive_roots_iter(15).is_err());
+    }
+    // 40 random rounds agree with the deterministic bases
+    #[test]
+    fn test_is_prime_default() {
+        use crate::sanity_checks::{is_prime_default, is_prime_deterministic};
+        for n in 0..20_000 {
+            assert_eq!(is_prime_default(n), is_prime_deterministic(n), "{}", n);
+        }
+        for n in [
+            2047,
+            3215031751,
+            (1 << 61) - 1,
+            18446744069414584321,
+            u64::MAX,
+        ] {
+            assert_eq!(is_prime_default(n), is_prime_deterministic(n), "{}", n);
+        }
+    }
+    // elements in the same coset of the order 4 subgroup mod 13 share a representative
+    #[test]
+    fn test_coset_representative() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::coset_representative;
+        // H = {1, 5, 8, 12}, the cosets are H, 2H = {2, 3, 10, 11} and 4H = {4, 6, 7, 9}
+        for (coset, rep) in [([1, 5, 8, 12], 1), ([2, 3, 10, 11], 2), ([4, 6, 7, 9], 4)] {
+            for x in coset {
+                assert_eq!(coset_representative(x, 13, 4).unwrap(), rep);
+                assert_eq!(coset_representative(x + 13, 13, 4).unwrap(), rep);
+            }
+        }
+        for x in 1..73 {
+            let rep = coset_representative(x, 73, 8).unwrap();
+            assert!(rep <= x);
+            assert_eq!(coset_representative(rep, 73, 8).unwrap(), rep);
+        }
+        assert_eq!(
+            coset_representative(0, 13, 4),
+            Err(SubgroupError::ZeroElement)
+        );
+        assert_eq!(
+            coset_representative(3, 13, 5),
+            Err(SubgroupError::NotFactor)
+        );
+    }
+    // random_prime rejects bit lengths that cannot work instead of panicking on the shift
+    #[test]
+    fn test_random_prime_bits() {
+        use crate::error::SubgroupError;
+        use crate::sanity_checks::{is_prime_deterministic, max_prime_bits, random_prime};
+        assert_eq!(max_prime_bits(), 63);
+        assert_eq!(random_prime(0), Err(SubgroupError::BitLengthUnsupported));
+        assert_eq!(random_prime(1), Err(SubgroupError::BitLengthUnsupported));
+        assert_eq!(random_prime(64), Err(SubgroupError::BitLengthUnsupported));
+        let p = random_prime(32).unwrap();
+        assert!(is_prime_deterministic(p));
+        assert_eq!(64 - p.leading_zeros(), 32);
+        assert!([2, 3].contains(&random_prime(2).unwrap()));
+        assert_eq!(64 - random_prime(63).unwrap().leading_zeros(), 63);
+    }
+    // the order of the schnorr subgroup is the largest prime factor of p-1
+    #[test]
+    fn test_prime_order_subgroup() {
+        use crate::multiplicative_subgruop::{is_in_subgroup, prime_order_subgroup};
+        use crate::sanity_checks::is_prime_deterministic;
+        for p in [7, 23, 101, 337, 7681] {
+            let (q, subgroup) = prime_order_subgroup(p).unwrap();
+            assert!(is_prime_deterministic(q));
+            assert_eq!((p - 1) % q, 0);
+            assert_eq!(subgroup.len() as u64, q);
+            assert_eq!(subgroup[0], 1);
+            assert!(subgroup.iter().all(|&x| is_in_subgroup(x, p, q)));
+        }
+        assert_eq!(prime_order_subgroup(23).unwrap().0, 11);
+        assert_eq!(prime_order_subgroup(337).unwrap().0, 7);
+        assert!(prime_order_subgroup(2).is_err());
+        assert!(prime_order_subgroup(21).is_err());
+    }
+    // the order 4 subgroup mod 13 is the same set whichever generator builds it
+    #[test]
+    fn test_subgroups_equal() {
+        use crate::multiplicative_subgruop::powers;
+        let from_5 = powers(5, 4, 13);
+        let from_8 = powers(8, 4, 13);
+        assert_ne!(from_5, from_8);
+        assert!(subgroups_equal(&from_5, &from_8, 13));
+        assert!(subgroups_equal(
+            &from_5,
+            &multiplicative_subgroup(13, 4).unwrap(),
+            13
+        ));
+        assert!(subgroups_equal(&from_5, &[14, 5, 8, 25], 13));
+        assert!(!subgroups_equal(&from_5, &powers(4, 6, 13), 13));
+        assert!(!subgroups_equal(&from_5, &[1, 5, 8], 13));
+    }
+    // parse "p,n" requests and report what is wrong with malformed ones
+    #[test]
+    fn test_parse_subgroup_request() {
+        use crate::error::ParseRequestError;
+        use crate::request::SubgroupRequest;
+        assert_eq!(
+            "7,3".parse::<SubgroupRequest>(),
+            Ok(SubgroupRequest { prime: 7, order: 3 })
+        );
+        assert_eq!(
+            " 13 , 4 ".parse::<SubgroupRequest>(),
+            Ok(SubgroupRequest {
+                prime: 13,
+                order: 4
+            })
+        );
+        assert_eq!(
+            "7".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::MissingComma)
+        );
+        assert_eq!(
+            "7;3".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::MissingComma)
+        );
+        assert_eq!(
+            "seven,3".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::InvalidNumber)
+        );
+        assert_eq!(
+            "7,".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::InvalidNumber)
+        );
+        assert_eq!(
+            "7,3,1".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::InvalidNumber)
+        );
+        assert_eq!(
+            "-7,3".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::InvalidNumber)
+        );
+        assert_eq!(
+            "18446744073709551616,3".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::Overflow)
+        );
+    }
+    // rotate so that a chosen element leads
+    #[test]
+    fn test_rotate_subgroup_to() {
+        use crate::multiplicative_subgruop::rotate_subgroup_to;
+        let mut elements = vec![8, 12, 5, 1];
+        assert!(rotate_subgroup_to(&mut elements, 1));
+        assert_eq!(elements, vec![1, 8, 12, 5]);
+        assert!(rotate_subgroup_to(&mut elements, 5));
+        assert_eq!(elements, vec![5, 1, 8, 12]);
+        assert!(!rotate_subgroup_to(&mut elements, 3));
+        assert_eq!(elements, vec![5, 1, 8, 12]);
+        assert!(!rotate_subgroup_to(&mut [], 1));
+    }
+    // the batch version matches calling mod_exp for each exponent
+    #[test]
+    fn test_mod_exp_batch() {
+        use crate::sanity_checks::mod_exp_batch;
+        let exps = [0, 1, 2, 3, 10, 255, 256, 1 << 40, u64::MAX, 12345678901234];
+        for (base, n) in [
+            (3, 7),
+            (5, 97),
+            (123456789, (1 << 61) - 1),
+            (u64::MAX, 18446744069414584321),
+        ] {
+            let batch = mod_exp_batch(base, &exps, n);
+            let single: Vec<u64> = exps.iter().map(|&e| mod_exp(base, e, n)).collect();
+            assert_eq!(batch, single);
+        }
+        assert!(mod_exp_batch(3, &[], 7).is_empty());
+    }
+    // a prime close to u64::MAX, the elements are checked against h^i computed directly
+    #[test]
+    fn test_multiplicative_subgroup_large_prime() {
+        // 2^64 - 2^32 + 1, p - 1 = 2^32 * 3 * 5 * 17 * 257 * 65537
+        let p = 18446744069414584321;
+        let n = 1 << 12;
+        let subgroup = multiplicative_subgroup(p, n).unwrap();
+        assert_eq!(subgroup.len() as u64, n);
+        let h = subgroup[1];
+        for (i, &x) in subgroup.iter().enumerate() {
+            assert_eq!(x, mod_exp(h, i as u64, p));
+        }
+        assert_eq!(mod_exp(h, n, p), 1);
+        assert_ne!(mod_exp(h, n / 2, p), 1);
+        let (subgroup, _) = multiplicative_subgroup_with_stats(p, 3 * 5 * 17).unwrap();
+        assert!(subgroup.iter().all(|&x| mod_exp(x, 3 * 5 * 17, p) == 1));
+    }
+    // element_at and index_of undo each other
+    #[test]
+    fn test_subgroup_context() {
+        use crate::multiplicative_subgruop::SubgroupContext;
+        use crate::sanity_checks::ceil_sqrt;
+        for (p, n) in [
+            (13, 4),
+            (73, 72),
+            (97, 12),
+            (7681, 256),
+            (18446744069414584321, 1 << 16),
+        ] {
+            let ctx = SubgroupContext::new(p, n).unwrap();
+            for i in [0, 1, 2, n / 2, n - 1] {
+                assert_eq!(ctx.index_of(ctx.element_at(i)), Some(i));
+            }
+        }
+        let ctx = SubgroupContext::new(73, 24).unwrap();
+        assert_eq!((ctx.p(), ctx.n()), (73, 24));
+        assert_eq!(ctx.element_at(1), ctx.generator());
+        for i in 0..24 {
+            assert_eq!(ctx.index_of(ctx.element_at(i)), Some(i));
+        }
+        assert_eq!(ctx.element_at(24), 1);
+        // 5 is a primitive root of 73 so it is not in the subgroup of size 24
+        assert_eq!(ctx.index_of(5), None);
+        assert_eq!(ctx.index_of(0), None);
+        assert!(SubgroupContext::new(73, 5).is_err());
+        assert!(SubgroupContext::new(73, 0).is_err());
+        // the baby step count is an exact integer square root, rounding the f64 one comes out a step short here
+        let n = (u32::MAX as u64).pow(2) + 1;
+        assert_eq!((n as f64).sqrt().ceil() as u64, u32::MAX as u64);
+        assert_eq!(ceil_sqrt(n), 1 << 32);
+        assert_eq!(ceil_sqrt(u64::MAX), 1 << 32);
+        for n in [0, 1, 2, 3, 4, 5, 15, 16, 17, 1 << 54, (1 << 54) + 1] {
+            let m = ceil_sqrt(n);
+            assert!(m * m >= n && (m == 0 || (m - 1) * (m - 1) < n), "{}", n);
+        }
+    }
+    // the builder trusts whatever primality test it is given
+    #[test]
+    fn test_subgroup_builder_primality_test() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::SubgroupBuilder;
+        let subgroup = SubgroupBuilder::new(13, 4)
+            .primality_test(|_| true)
+            .build()
+            .unwrap();
+        assert!(subgroups_equal(&subgroup, &[1, 5, 8, 12], 13));
+        assert_eq!(
+            SubgroupBuilder::new(13, 4)
+                .primality_test(|_| false)
+                .build(),
+            Err(SubgroupError::NotPrime)
+        );
+        let subgroup = SubgroupBuilder::new(7, 3).build().unwrap();
+        assert!(subgroups_equal(&subgroup, &[1, 2, 4], 7));
+        assert_eq!(
+            SubgroupBuilder::new(15, 2).build(),
+            Err(SubgroupError::NotPrime)
+        );
+        assert_eq!(
+            SubgroupBuilder::new(13, 5).build(),
+            Err(SubgroupError::NotFactor)
+        );
+        // every unit modulo the carmichael number 561 has order dividing 80, so nothing passes as a primitive root
+        // and the builder reports that instead of building {1}
+        assert_eq!(
+            SubgroupBuilder::new(561, 4)
+                .primality_test(|_| true)
+                .build(),
+            Err(SubgroupError::GeneratorNotFound)
+        );
+        assert_eq!(
+            crate::field::random_primitive_root(561),
+            Err(SubgroupError::GeneratorNotFound)
+        );
+        assert_eq!(
+            crate::field::random_primitive_root(0),
+            Err(SubgroupError::NotPrime)
+        );
+    }
+    // 998244353 = 119 * 2^23 + 1 supports power of two sizes up to 2^23
+    #[test]
+    fn test_smallest_power_of_two_order() {
+        use crate::multiplicative_subgruop::smallest_power_of_two_order;
+        let p = 998244353;
+        assert_eq!(smallest_power_of_two_order(p, 0), Some(1));
+        assert_eq!(smallest_power_of_two_order(p, 1), Some(1));
+        assert_eq!(smallest_power_of_two_order(p, 1000), Some(1024));
+        assert_eq!(smallest_power_of_two_order(p, 1024), Some(1024));
+        assert_eq!(smallest_power_of_two_order(p, 1 << 23), Some(1 << 23));
+        assert_eq!(smallest_power_of_two_order(p, (1 << 23) + 1), None);
+        assert_eq!(smallest_power_of_two_order(7681, 300), Some(512));
+        assert_eq!(smallest_power_of_two_order(7681, 513), None);
+        assert_eq!(smallest_power_of_two_order(p, u64::MAX), None);
+    }
+    // the Subgroup wrapper indexes and iterates like the plain Vec
+    #[test]
+    fn test_subgroup_wrapper() {
+        use crate::multiplicative_subgruop::Subgroup;
+        let subgroup = Subgroup::new(13, 4).unwrap();
+        assert_eq!((subgroup.p(), subgroup.n()), (13, 4));
+        assert_eq!(subgroup.len(), 4);
+        assert!(!subgroup.is_empty());
+        assert_eq!(subgroup[0], 1);
+        assert_eq!(subgroup[1], subgroup.generator());
+        assert_eq!(subgroup[2], 12);
+        assert_eq!(subgroup.iter().filter(|&&x| x == 1).count(), 1);
+        let mut product = 1;
+        for &x in &subgroup {
+            product = product * x % 13;
+        }
+        // the product of the subgroup of even size 4 is -1
+        assert_eq!(product, 12);
+        assert!(subgroups_equal(&subgroup, &[1, 5, 8, 12], 13));
+        let collected: Vec<u64> = subgroup.clone().into_iter().collect();
+        assert_eq!(&collected[..], &subgroup[..]);
+        assert!(Subgroup::new(13, 5).is_err());
+    }
+    // only powers of two pass, with their log2
+    #[test]
+    fn test_require_power_of_two() {
+        use crate::error::SubgroupError;
+        use crate::ntt::require_power_of_two;
+        assert_eq!(require_power_of_two(8), Ok(3));
+        assert_eq!(require_power_of_two(1), Ok(0));
+        assert_eq!(require_power_of_two(1 << 63), Ok(63));
+        assert_eq!(require_power_of_two(6), Err(SubgroupError::NotPowerOfTwo));
+        assert_eq!(require_power_of_two(0), Err(SubgroupError::NotPowerOfTwo));
+    }
+    // the intersection of two subgroups is the subgroup of size gcd(m, n)
+    #[test]
+    fn test_subgroup_intersection() {
+        use crate::multiplicative_subgruop::{is_subgroup_of, subgroup_intersection};
+        use crate::sanity_checks::gcd;
+        let p = 73;
+        let divisors = factors(p - 1);
+        for &m in &divisors {
+            for &n in &divisors {
+                let intersection = subgroup_intersection(p, m, n).unwrap();
+                let expected = multiplicative_subgroup(p, gcd(m, n)).unwrap();
+                assert!(subgroups_equal(&intersection, &expected, p));
+                let a = multiplicative_subgroup(p, m).unwrap();
+                let b = multiplicative_subgroup(p, n).unwrap();
+                let both: Vec<u64> = a.iter().copied().filter(|x| b.contains(x)).collect();
+                assert!(subgroups_equal(&intersection, &both, p));
+                assert_eq!(is_subgroup_of(m, n), both.len() as u64 == m);
+            }
+        }
+        assert!(is_subgroup_of(3, 12));
+        assert!(!is_subgroup_of(5, 12));
+        assert!(!is_subgroup_of(0, 12));
+        assert!(subgroup_intersection(p, 5, 8).is_err());
+    }
+    // a primitive root raised to the projection exponent has order exactly n
+    #[test]
+    fn test_projection_exponent() {
+        use crate::error::SubgroupError;
+        use crate::field::find_primitive_root;
+        use crate::multiplicative_subgruop::{cofactor, projection_exponent};
+        let order = |x: u64, p: u64| (1..p).find(|&d| mod_exp(x, d, p) == 1).unwrap();
+        for p in [7, 13, 73, 337] {
+            for n in factors(p - 1) {
+                let h = mod_exp(
+                    find_primitive_root(p).unwrap(),
+                    projection_exponent(p, n).unwrap(),
+                    p,
+                );
+                assert_eq!(order(h, p), n);
+            }
+        }
+        assert_eq!(projection_exponent(13, 4), Ok(3));
+        assert_eq!(projection_exponent(13, 5), Err(SubgroupError::NotFactor));
+        assert_eq!(projection_exponent(12, 2), Err(SubgroupError::NotPrime));
+        // cofactor only checks the divisibility, the primality of p is left to the caller
+        assert_eq!(cofactor(13, 4), Ok(3));
+        assert_eq!(cofactor(9, 4), Ok(2));
+        for (p, n) in [(13, 5), (13, 0), (0, 1), (1, 1)] {
+            assert_eq!(cofactor(p, n), Err(SubgroupError::NotFactor));
+        }
+    }
+    // n = p-1 gives the whole group and n = 1 only the identity
+    #[test]
+    fn test_multiplicative_subgroup_full_group() {
+        let group = multiplicative_subgroup(7, 6).unwrap();
+        assert!(subgroups_equal(&group, &[1, 2, 3, 4, 5, 6], 7));
+        assert_eq!(group[0], 1);
+        assert!(is_primitive_root(7, group[1]));
+        for p in [2, 3, 13, 73, 337] {
+            let group = multiplicative_subgroup(p, p - 1).unwrap();
+            let all: Vec<u64> = (1..p).collect();
+            assert_eq!(group.len() as u64, p - 1);
+            assert!(subgroups_equal(&group, &all, p));
+            assert_eq!(multiplicative_subgroup(p, 1).unwrap(), vec![1]);
+        }
+    }
+    // Prime only holds primes and the from_prime constructors skip the primality check
+    #[test]
+    fn test_prime_wrapper() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::{
+            multiplicative_subgroup_from_prime, Subgroup, SubgroupBuilder, SubgroupContext,
+        };
+        use crate::sanity_checks::Prime;
+        assert_eq!(Prime::new(9), None);
+        assert_eq!(Prime::new(1), None);
+        assert_eq!(Prime::new(11).map(Prime::get), Some(11));
+        let p = Prime::new(11).unwrap();
+        let subgroup = Subgroup::from_prime(p, 5).unwrap();
+        assert!(subgroups_equal(&subgroup, &[1, 3, 4, 5, 9], 11));
+        assert_eq!(
+            Subgroup::from_prime(p, 3).unwrap_err(),
+            SubgroupError::NotFactor
+        );
+        assert_eq!(Subgroup::new(9, 2).unwrap_err(), SubgroupError::NotPrime);
+        let subgroup = multiplicative_subgroup_from_prime(p, 5).unwrap();
+        assert!(subgroups_equal(&subgroup, &[1, 3, 4, 5, 9], 11));
+        assert_eq!(
+            multiplicative_subgroup_from_prime(p, 3),
+            Err(SubgroupError::NotFactor)
+        );
+        let subgroup = SubgroupBuilder::from_prime(p, 5).build().unwrap();
+        assert!(subgroups_equal(&subgroup, &[1, 3, 4, 5, 9], 11));
+        assert_eq!(
+            SubgroupBuilder::from_prime(p, 5)
+                .max_reasonable_size(4)
+                .build(),
+            Err(SubgroupError::TooLarge)
+        );
+        let context = SubgroupContext::from_prime(p, 5).unwrap();
+        assert_eq!((context.p(), context.n()), (11, 5));
+        assert_eq!(context.index_of(context.element_at(3)), Some(3));
+        assert_eq!(
+            SubgroupContext::from_prime(p, 3).map(|context| context.n()),
+            Err(SubgroupError::NotFactor)
+        );
+        assert_eq!(
+            SubgroupContext::new(9, 2).map(|context| context.n()),
+            Err(SubgroupError::NotPrime)
+        );
+    }
+    // the prime found has a subgroup of the requested order
+    #[test]
+    fn test_find_prime_with_subgroup() {
+        use crate::multiplicative_subgruop::find_prime_with_subgroup;
+        use crate::sanity_checks::is_prime_deterministic;
+        for (order, min_bits) in [
+            (3, 0),
+            (5, 10),
+            (1 << 20, 30),
+            (3 * 5 * 7, 40),
+            (1000003, 50),
+        ] {
+            let p = find_prime_with_subgroup(order, min_bits).unwrap();
+            assert!(is_prime_deterministic(p));
+            assert_eq!((p - 1) % order, 0);
+            assert!(p >= 1 << min_bits);
+            // nothing smaller of the same form was skipped
+            let mut q = p - order;
+            while q >= 1 << min_bits && q > order {
+                assert!(!is_prime_deterministic(q));
+                q -= order;
+            }
+        }
+        assert_eq!(find_prime_with_subgroup(3, 2), Some(7));
+        assert_eq!(find_prime_with_subgroup(0, 10), None);
+        assert_eq!(find_prime_with_subgroup(3, 64), None);
+        let p = find_prime_with_subgroup(1 << 16, 20).unwrap();
+        assert!(crate::multiplicative_subgruop::Subgroup::new(p, 1 << 16).is_ok());
+    }
+    // with the log feature the search and the miller rabin rounds emit trace messages
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_trace_logging() {
+        use std::sync::Mutex;
+        static MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+        struct CapturingLogger;
+        impl log::Log for CapturingLogger {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+            fn log(&self, record: &log::Record) {
+                MESSAGES.lock().unwrap().push(record.args().to_string());
+            }
+            fn flush(&self) {}
+        }
+        static LOGGER: CapturingLogger = CapturingLogger;
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        // the miller rabin rounds are asked for directly, the subgroup search does not have to run them
+        assert!(crate::sanity_checks::is_prime(1009, 5));
+        multiplicative_subgroup(1009, 8).unwrap();
+        let messages = MESSAGES.lock().unwrap();
+        assert!(messages
+            .iter()
+            .any(|m| m.starts_with("miller rabin n = 1009 ")));
+        assert!(messages
+            .iter()
+            .any(|m| m.starts_with("primitive root candidate p = 1009 ")
+                && m.ends_with("accepted = true")));
+    }
+    // rows * cols = n and reading the rows in order gives the powers of one generator
+    #[test]
+    fn test_subgroup_matrix() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::subgroup_matrix;
+        let matrix = subgroup_matrix(7681, 256, 16).unwrap();
+        assert_eq!(matrix.len(), 16);
+        assert!(matrix.iter().all(|row| row.len() == 16));
+        let flat: Vec<u64> = matrix.concat();
+        let h = flat[1];
+        for (i, &x) in flat.iter().enumerate() {
+            assert_eq!(x, mod_exp(h, i as u64, 7681));
+        }
+        assert_eq!(mod_exp(h, 256, 7681), 1);
+        assert_eq!(mod_exp(h, 128, 7681), 7680);
+        let matrix = subgroup_matrix(13, 12, 3).unwrap();
+        assert_eq!((matrix.len(), matrix[0].len()), (4, 3));
+        assert_eq!(subgroup_matrix(13, 12, 5), Err(SubgroupError::InvalidShape));
+        assert_eq!(subgroup_matrix(13, 12, 0), Err(SubgroupError::InvalidShape));
+        assert_eq!(subgroup_matrix(13, 5, 5), Err(SubgroupError::NotFactor));
+    }
+    // reducing the exponent modulo the order gives the same value as exponentiating directly
+    #[test]
+    fn test_mod_exp_in_subgroup() {
+        use crate::multiplicative_subgruop::{mod_exp_in_subgroup, Subgroup};
+        let p = 18446744069414584321;
+        let subgroup = Subgroup::new(p, 1 << 20).unwrap();
+        let h = subgroup.generator();
+        for i in [0, 1, 7, 12345, (1 << 20) - 1] {
+            for exp in [0, 1, 1 << 20, (1 << 40) + 3, u64::MAX, u64::MAX - 12345] {
+                let expected = mod_exp(mod_exp(h, i, p), exp, p);
+                assert_eq!(mod_exp_in_subgroup(h, i, exp, p, 1 << 20), Ok(expected));
+            }
+        }
+        assert_eq!(mod_exp_in_subgroup(5, 3, 7, 13, 4), Ok(5));
+    }
+    // the fast order check agrees with the order found by scanning divisors
+    #[test]
+    fn test_has_order() {
+        use crate::primitive_root::{has_order, multiplicative_order};
+        assert_eq!(multiplicative_order(2, 7), Some(3));
+        assert_eq!(multiplicative_order(3, 7), Some(6));
+        assert_eq!(multiplicative_order(85, 337), Some(8));
+        assert_eq!(multiplicative_order(2, 15), Some(4));
+        assert_eq!(multiplicative_order(3, 15), None);
+        for p in [13, 73, 337] {
+            for g in 1..p {
+                let order = multiplicative_order(g, p).unwrap();
+                for n in factors(p - 1) {
+                    assert_eq!(has_order(g, n, p), n == order, "{} {} {}", g, n, p);
+                }
+            }
+        }
+        assert!(!has_order(0, 1, 7));
+        assert!(!has_order(2, 0, 7));
+        assert!(has_order(2, 4, 15));
+        assert!(!has_order(2, 2, 15));
+    }
+    // nothing that is returned depends on hash iteration order, repeated runs give the same output
+    #[test]
+    fn test_repeated_runs_identical() {
+        use crate::multiplicative_subgruop::{coset_representative, subgroup_intersection};
+        let expected = multiplicative_subgroup_deterministic(337, 48).unwrap();
+        let mut sorted_expected = expected.clone();
+        sorted_expected.sort();
+        for _ in 0..50 {
+            assert_eq!(
+                multiplicative_subgroup_deterministic(337, 48).unwrap(),
+                expected
+            );
+            let mut random = multiplicative_subgroup(337, 48).unwrap();
+            random.sort();
+            assert_eq!(random, sorted_expected);
+            let mut intersection = subgroup_intersection(337, 48, 42).unwrap();
+            intersection.sort();
+            assert_eq!(intersection, vec![1, 128, 129, 208, 209, 336]);
+            assert_eq!(coset_representative(5, 337, 48).unwrap(), 5);
+        }
+    }
+    // order n subgroups of different primes are isomorphic once both exist
+    #[test]
+    fn test_subgroups_isomorphic() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::subgroups_isomorphic;
+        assert_eq!(subgroups_isomorphic(7, 13, 3), Ok(true));
+        assert_eq!(subgroups_isomorphic(13, 73, 4), Ok(true));
+        assert_eq!(subgroups_isomorphic(7, 7, 6), Ok(true));
+        assert_eq!(
+            subgroups_isomorphic(7, 11, 3),
+            Err(SubgroupError::NotFactor)
+        );
+        assert_eq!(
+            subgroups_isomorphic(11, 7, 3),
+            Err(SubgroupError::NotFactor)
+        );
+        assert_eq!(subgroups_isomorphic(7, 15, 2), Err(SubgroupError::NotPrime));
+    }
+    // x times its subgroup inverse is 1
+    #[test]
+    fn test_subgroup_inverse() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::subgroup_inverse;
+        use crate::sanity_checks::mod_mul;
+        let p = 18446744069414584321;
+        for &x in multiplicative_subgroup(p, 64).unwrap().iter() {
+            assert_eq!(mod_mul(x, subgroup_inverse(x, p, 64).unwrap(), p), 1);
+        }
+        for x in multiplicative_subgroup(13, 4).unwrap() {
+            assert_eq!(mod_mul(x, subgroup_inverse(x, 13, 4).unwrap(), 13), 1);
+        }
+        assert_eq!(
+            subgroup_inverse(2, 13, 4),
+            Err(SubgroupError::NotInSubgroup)
+        );
+        assert_eq!(
+            subgroup_inverse(0, 13, 4),
+            Err(SubgroupError::NotInSubgroup)
+        );
+        assert_eq!(subgroup_inverse(5, 13, 5), Err(SubgroupError::NotFactor));
+    }
+    // the same prime written in decimal, hex and with underscores
+    #[test]
+    fn test_parse_subgroup_request_hex() {
+        use crate::error::ParseRequestError;
+        use crate::request::SubgroupRequest;
+        let expected = Ok(SubgroupRequest {
+            prime: 4294967291,
+            order: 5,
+        });
+        assert_eq!("4294967291,5".parse::<SubgroupRequest>(), expected);
+        assert_eq!("0xFFFFFFFB,5".parse::<SubgroupRequest>(), expected);
+        assert_eq!("0xffff_fffb,0x5".parse::<SubgroupRequest>(), expected);
+        assert_eq!("0XFFFF_FFFB, 5".parse::<SubgroupRequest>(), expected);
+        assert_eq!("4_294_967_291,5".parse::<SubgroupRequest>(), expected);
+        assert_eq!(
+            "0x,5".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::InvalidNumber)
+        );
+        assert_eq!(
+            "0xFG,5".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::InvalidNumber)
+        );
+        assert_eq!(
+            "_7,5".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::InvalidNumber)
+        );
+        assert_eq!(
+            "0x_7,5".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::InvalidNumber)
+        );
+        assert_eq!(
+            "7_,5".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::InvalidNumber)
+        );
+        for invalid in ["+7,5", "0x+FF,5", "1__0,5", "-7,5", "7,+5", "0x_,5", ",5"] {
+            assert_eq!(
+                invalid.parse::<SubgroupRequest>(),
+                Err(ParseRequestError::InvalidNumber),
+                "{}",
+                invalid
+            );
+        }
+        assert_eq!(
+            "0x1_0000_0000_0000_0000,5".parse::<SubgroupRequest>(),
+            Err(ParseRequestError::Overflow)
+        );
+    }
+    // invalid inputs come back as errors instead of panicking
+    #[test]
+    fn test_adversarial_inputs() {
+        use crate::error::SubgroupError;
+        use crate::field::{
+            count_primitive_roots, generate_candidate, is_primitive_root_with_factors,
+            primes_with_primitive_root,
+        };
+        use crate::multiplicative_subgruop::{
+            is_in_subgroup, mod_exp_in_subgroup, subgroup_pow, to_centered, Subgroup,
+            SubgroupBuilder,
+        };
+        use crate::primitive_root::{has_order, prime_factorization};
+        for (p, n) in [
+            (0, 0),
+            (0, 1),
+            (1, 1),
+            (13, 0),
+            (13, 5),
+            (15, 2),
+            (u64::MAX, 3),
+        ] {
+            assert!(multiplicative_subgroup(p, n).is_err(), "{} {}", p, n);
+            assert!(
+                multiplicative_subgroup_deterministic(p, n).is_err(),
+                "{} {}",
+                p,
+                n
+            );
+            assert!(Subgroup::new(p, n).is_err(), "{} {}", p, n);
+        }
+        assert!(multiplicative_subgroup_u128(13, 0).is_err());
+        assert!(multiplicative_subgroup_u128(0, 1).is_err());
+        assert_eq!(
+            SubgroupBuilder::new(0, 1).primality_test(|_| true).build(),
+            Err(SubgroupError::NotPrime)
+        );
+        assert_eq!(
+            SubgroupBuilder::new(13, 0).build(),
+            Err(SubgroupError::NotFactor)
+        );
+        assert_eq!(multiplicative_subgroup(2, 1).unwrap(), vec![1]);
+        assert_eq!(multiplicative_subgroup(3, 2).unwrap(), vec![1, 2]);
+        assert_eq!(generate_candidate(3), 2);
+        assert_eq!(generate_candidate(2), 1);
+        assert_eq!(generate_candidate(0), 0);
+        assert_eq!(count_primitive_roots(0), 0);
+        assert!(!is_primitive_root(0, 3));
+        assert!(!is_primitive_root(1, 0));
+        assert!(!has_order(1, 1, 0));
+        // modulo 1 everything has order 1
+        assert!(has_order(5, 1, 1));
+        assert!(!has_order(5, 2, 1));
+        assert!(!has_order(2, 6, 1));
+        assert!(!is_in_subgroup(3, 0, 2));
+        assert!(!is_primitive_root_with_factors(7, 3, &[0, 2, 3]));
+        assert_eq!(subgroup_pow(5, 3, 7, 13, 0), Err(SubgroupError::NotFactor));
+        assert_eq!(subgroup_pow(5, 3, 7, 0, 4), Err(SubgroupError::NotPrime));
+        assert_eq!(
+            mod_exp_in_subgroup(5, 3, 7, 13, 0),
+            Err(SubgroupError::NotFactor)
+        );
+        assert_eq!(to_centered(3, 0), Err(SubgroupError::NotPrime));
+        assert_eq!(
+            primes_with_primitive_root(2, u64::MAX),
+            Err(SubgroupError::TooLarge)
+        );
+        assert_eq!(
+            prime_factorization(u64::MAX),
+            vec![
+                (3, 1),
+                (5, 1),
+                (17, 1),
+                (257, 1),
+                (641, 1),
+                (65537, 1),
+                (6700417, 1)
+            ]
+        );
+    }
+    // TryFrom builds a validated Subgroup from a (p, n) tuple
+    #[test]
+    fn test_subgroup_try_from() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::Subgroup;
+        let subgroup = Subgroup::try_from((7, 3)).unwrap();
+        assert!(subgroups_equal(&subgroup, &[1, 2, 4], 7));
+        assert_eq!(
+            Subgroup::try_from((8, 3)).unwrap_err(),
+            SubgroupError::NotPrime
+        );
+        assert_eq!(
+            Subgroup::try_from((7, 4)).unwrap_err(),
+            SubgroupError::NotFactor
+        );
+        let subgroup: Result<Subgroup, _> = (13, 4).try_into();
+        assert_eq!(subgroup.unwrap().len(), 4);
+    }
+    // the product of the subgroup is 1 for odd orders and p-1 for even orders
+    #[test]
+    fn test_subgroup_product() {
+        use crate::multiplicative_subgruop::subgroup_product;
+        for p in [3, 7, 13, 73, 337, 7681] {
+            for n in factors(p - 1) {
+                let expected = if n % 2 == 0 { p - 1 } else { 1 };
+                assert_eq!(subgroup_product(p, n).unwrap(), expected, "{} {}", p, n);
+            }
+        }
+        // n = p-1 is wilson's theorem itself
+        assert_eq!(subgroup_product(13, 12).unwrap(), 12);
+        assert_eq!(subgroup_product(2, 1).unwrap(), 1);
+        assert!(subgroup_product(13, 5).is_err());
+    }
+    // the smallest primitive roots in ascending order
+    #[test]
+    fn test_primitive_roots_take() {
+        use crate::field::primitive_roots_take;
+        assert_eq!(primitive_roots_take(7, 2).unwrap(), vec![3, 5]);
+        assert_eq!(primitive_roots_take(7, 5).unwrap(), vec![3, 5]);
+        assert_eq!(primitive_roots_take(13, 3).unwrap(), vec![2, 6, 7]);
+        assert_eq!(primitive_roots_take(2, 1).unwrap(), vec![1]);
+        assert_eq!(primitive_roots_take(13, 0).unwrap(), Vec::<u64>::new());
+        assert!(primitive_roots_take(15, 2).is_err());
+    }
+    // mersenne primes 2^31 - 1 and 2^61 - 1, p-1 is smooth so factoring and root finding stay fast
+    #[test]
+    fn test_mersenne_primes() {
+        use crate::error::SubgroupError;
+        use crate::field::smallest_primitive_root;
+        use crate::multiplicative_subgruop::{smallest_power_of_two_order, Subgroup};
+        use crate::primitive_root::multiplicative_order;
+        let m31 = (1 << 31) - 1;
+        let m61 = (1 << 61) - 1;
+        assert_eq!(factors(m31 - 1).len(), 192);
+        assert_eq!(factors(m61 - 1).len(), 9216);
+        assert!(factors(m61 - 1).windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(smallest_primitive_root(m31), Some(7));
+        assert_eq!(smallest_primitive_root(m61), Some(37));
+        assert_eq!(multiplicative_order(7, m31), Some(m31 - 1));
+        assert_eq!(multiplicative_order(37, m61), Some(m61 - 1));
+
+        // p-1 = 2 * odd for a mersenne prime so the only power of two subgroups have order 1 and 2
+        assert_eq!(multiplicative_subgroup(m31, 2).unwrap(), vec![1, m31 - 1]);
+        assert_eq!(Subgroup::new(m31, 4).unwrap_err(), SubgroupError::NotFactor);
+        assert_eq!(smallest_power_of_two_order(m31, 2), Some(2));
+        assert_eq!(smallest_power_of_two_order(m31, 3), None);
+
+        let subgroup = multiplicative_subgroup(m61, 450).unwrap();
+        assert_eq!(subgroup.len(), 450);
+        assert!(subgroup.iter().all(|&x| mod_exp(x, 450, m61) == 1));
+        assert_eq!(multiplicative_order(subgroup[1], m61), Some(450));
+    }
+    // every stride root has exactly the order of its radix and the roots of different strides agree
+    #[test]
+    fn test_stride_root() {
+        use crate::error::SubgroupError;
+        use crate::ntt::stride_root;
+        use crate::primitive_root::multiplicative_order;
+        // p-1 = 2^23 * 7 * 17
+        let p = 998244353;
+        let n = 4 * 7 * 17;
+        for radix in [1, 2, 4, 7, 17, 28, n] {
+            let w = stride_root(p, n, radix).unwrap();
+            assert_eq!(multiplicative_order(w, p), Some(radix));
+        }
+        // the radix 7 root is a power of the radix 28 root
+        let w28 = stride_root(p, n, 28).unwrap();
+        assert_eq!(stride_root(p, n, 7).unwrap(), mod_exp(w28, 4, p));
+        assert_eq!(stride_root(p, n, 3), Err(SubgroupError::NotFactor));
+        assert_eq!(stride_root(p, n, 0), Err(SubgroupError::NotFactor));
+        assert_eq!(stride_root(p, 3, 3), Err(SubgroupError::NotFactor));
+        assert_eq!(stride_root(15, 2, 2), Err(SubgroupError::NotPrime));
+    }
+    // the smallest prime at or above min with 2^k dividing p-1, and the two-adicity it relies on
+    #[test]
+    fn test_find_transform_prime() {
+        use crate::ntt::{find_transform_prime, two_adicity};
+        assert_eq!(two_adicity(998244352), 23);
+        assert_eq!(two_adicity(7), 0);
+        assert_eq!(two_adicity(96), 5);
 
-fn main() {
-    println!("Hello, world!");
-}
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::multiplicative_subgruop::multiplicative_subgroup;
-    use crate::primitive_root::factors;
-    use field::is_generator;
+        assert_eq!(find_transform_prime(0, 0), Some(2));
+        assert_eq!(find_transform_prime(0, 1), Some(3));
+        assert_eq!(find_transform_prime(1 << 29, 23), Some(595591169));
+        assert_eq!(find_transform_prime(998244353, 23), Some(998244353));
+        for (min, k) in [(1000, 4), (1 << 40, 20), (1 << 62, 32), (12345, 0)] {
+            let p = find_transform_prime(min, k).unwrap();
+            assert!(p >= min);
+            assert!(is_prime_u128(p as u128));
+            assert!(two_adicity(p - 1) >= k);
+        }
+        assert_eq!(find_transform_prime(u64::MAX - 10, 8), None);
+        assert_eq!(find_transform_prime(0, 64), None);
+    }
+    // the boxed errors of every entry point are the typed one converted, so all report the same message
+    #[test]
+    fn test_typed_and_boxed_errors() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::multiplicative_subgroup_with_stats;
+        for (p, n, expected) in [
+            (15, 2, SubgroupError::NotPrime),
+            (13, 5, SubgroupError::NotFactor),
+            (13, 0, SubgroupError::NotFactor),
+        ] {
+            let typed = multiplicative_subgroup_with_stats(p, n).unwrap_err();
+            assert_eq!(typed, expected);
+            let boxed = multiplicative_subgroup(p, n).unwrap_err();
+            assert_eq!(boxed.to_string(), typed.to_string());
+            assert_eq!(boxed.downcast_ref::<SubgroupError>(), Some(&expected));
+            for boxed in [
+                multiplicative_subgroup_deterministic(p, n).unwrap_err(),
+                multiplicative_subgroup_result(p, n).unwrap_err(),
+                multiplicative_subgroup_u128(p as u128, n).unwrap_err(),
+            ] {
+                assert_eq!(boxed.downcast_ref::<SubgroupError>(), Some(&expected));
+            }
+        }
+        let (subgroup, _) = multiplicative_subgroup_with_stats(13, 4).unwrap();
+        assert!(subgroups_equal(&subgroup, &[1, 5, 8, 12], 13));
+        assert!(subgroups_equal(
+            &multiplicative_subgroup(13, 4).unwrap(),
+            &subgroup,
+            13
+        ));
+    }
+    // elements move to the bit reversed index and a second pass puts them back
+    #[test]
+    fn test_bit_reverse_permute() {
+        use crate::error::SubgroupError;
+        use crate::ntt::bit_reverse_permute;
+        let mut data: Vec<u32> = (0..8).collect();
+        bit_reverse_permute(&mut data).unwrap();
+        assert_eq!(data, vec![0, 4, 2, 6, 1, 5, 3, 7]);
+        // applying it twice gives the original order back
+        for len in [1, 2, 4, 16, 1024] {
+            let original: Vec<usize> = (0..len).collect();
+            let mut data = original.clone();
+            bit_reverse_permute(&mut data).unwrap();
+            bit_reverse_permute(&mut data).unwrap();
+            assert_eq!(data, original);
+        }
+        let mut words = vec!["a", "b", "c", "d"];
+        bit_reverse_permute(&mut words).unwrap();
+        assert_eq!(words, vec!["a", "c", "b", "d"]);
+        let mut data = vec![1, 2, 3];
+        assert_eq!(
+            bit_reverse_permute(&mut data),
+            Err(SubgroupError::NotPowerOfTwo)
+        );
+        assert_eq!(data, vec![1, 2, 3]);
+        assert_eq!(
+            bit_reverse_permute::<u64>(&mut []),
+            Err(SubgroupError::NotPowerOfTwo)
+        );
+    }
+    // the two-adicity, largest odd factor and bit length of well known transform primes
+    #[test]
+    fn test_transform_prime_quality() {
+        use crate::error::SubgroupError;
+        use crate::ntt::{transform_prime_quality, PrimeQuality};
+        // 998244353 = 119 * 2^23 + 1 and 119 = 7 * 17
+        let expected = PrimeQuality {
+            two_adicity: 23,
+            largest_odd_factor: 17,
+            bits: 30,
+        };
+        assert_eq!(transform_prime_quality(998244353), Ok(expected));
+        // the goldilocks prime 2^64 - 2^32 + 1, p-1 = 2^32 * 3 * 5 * 17 * 257 * 65537
+        let q = transform_prime_quality(0xffff_ffff_0000_0001).unwrap();
+        assert_eq!(
+            (q.two_adicity, q.largest_odd_factor, q.bits),
+            (32, 65537, 64)
+        );
+        // fermat prime, p-1 is a power of two
+        let q = transform_prime_quality(65537).unwrap();
+        assert_eq!((q.two_adicity, q.largest_odd_factor, q.bits), (16, 1, 17));
+        let q = transform_prime_quality((1 << 61) - 1).unwrap();
+        assert_eq!((q.two_adicity, q.largest_odd_factor, q.bits), (1, 1321, 61));
+        assert_eq!(transform_prime_quality(2).unwrap().largest_odd_factor, 1);
+        assert_eq!(
+            transform_prime_quality(998244351),
+            Err(SubgroupError::NotPrime)
+        );
+    }
+    // supplied factors of p-1 give the same subgroup as factoring, and bad factor lists are refused
+    #[test]
+    fn test_multiplicative_subgroup_with_factors() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::multiplicative_subgroup_with_factors;
+        // 998244353 - 1 = 2^23 * 7 * 17
+        let p = 998244353;
+        let subgroup = multiplicative_subgroup_with_factors(p, 7 * 16, &[2, 7, 17]).unwrap();
+        assert_eq!(subgroup.len(), 112);
+        assert_eq!(subgroup[0], 1);
+        assert!(subgroups_equal(
+            &subgroup,
+            &multiplicative_subgroup_deterministic(p, 112).unwrap(),
+            p
+        ));
+        // the order of the factors does not matter
+        assert!(multiplicative_subgroup_with_factors(p, 4, &[17, 2, 7]).is_ok());
+        assert_eq!(
+            multiplicative_subgroup_with_factors(3, 2, &[2]).unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            multiplicative_subgroup_with_factors(2, 1, &[]).unwrap(),
+            vec![1]
+        );
 
+        // missing factors, a prime that does not divide p-1, composites and 0
+        for wrong in [
+            &[2, 7][..],
+            &[2, 7, 17, 3],
+            &[2, 119],
+            &[4, 7, 17],
+            &[],
+            &[2, 7, 17, 0],
+        ] {
+            assert_eq!(
+                multiplicative_subgroup_with_factors(p, 4, wrong),
+                Err(SubgroupError::InvalidFactors)
+            );
+        }
+        assert_eq!(
+            multiplicative_subgroup_with_factors(p, 3, &[2, 7, 17]),
+            Err(SubgroupError::NotFactor)
+        );
+        assert_eq!(
+            multiplicative_subgroup_with_factors(15, 2, &[2, 7]),
+            Err(SubgroupError::NotPrime)
+        );
+    }
+    // with the random number generator unavailable everything falls back to deterministic choices
+    // the override is per thread so the other tests keep their randomness
     #[test]
-    fn test_factors() {
-        assert_eq!(factors(12), vec![1, 2, 3, 4, 6, 12]);
-        assert_eq!(factors(15), vec![1, 3, 5, 15]);
-        assert_eq!(factors(17), vec![1, 17]);
-        assert_eq!(factors(24), vec![1, 2, 3, 4, 6, 8, 12, 24]);
-        assert_eq!(factors(6), vec![1, 2, 3, 6]);
+    fn test_deterministic_fallback_without_rng() {
+        use crate::error::SubgroupError;
+        use crate::field::{random_primitive_root, smallest_primitive_root};
+        use crate::multiplicative_subgruop::{
+            multiplicative_subgroup_u128, multiplicative_subgroup_with_factors,
+            multiplicative_subgroup_with_stats, subgroup_generator, SubgroupBuilder,
+        };
+        use crate::sanity_checks::{is_prime, random_prime, rng_available, RNG_DISABLED};
+        RNG_DISABLED.with(|disabled| disabled.set(true));
+        assert!(!rng_available());
+
+        let p = 998244353;
+        let (subgroup, stats) = multiplicative_subgroup_with_stats(p, 7).unwrap();
+        assert_eq!(stats.candidates_tried, 0);
+        assert_eq!(stats.generator, 3);
+        assert_eq!(
+            subgroup,
+            multiplicative_subgroup_deterministic(p, 7).unwrap()
+        );
+        assert_eq!(multiplicative_subgroup(p, 7).unwrap(), subgroup);
+        assert_eq!(
+            multiplicative_subgroup_with_factors(p, 7, &[2, 7, 17]).unwrap(),
+            subgroup
+        );
+        assert_eq!(subgroup_generator(p, 7).unwrap(), subgroup[1]);
+        assert_eq!(random_primitive_root(p).ok(), smallest_primitive_root(p));
+        let large = multiplicative_subgroup_u128(p as u128, 7).unwrap();
+        assert!(large.iter().all(|&x| mod_exp(x as u64, 7, p) == 1));
+        assert_eq!(large.len(), 7);
+
+        assert!(is_prime(p, 5));
+        assert!(!is_prime(p - 2, 5));
+        // the smallest 16 bit prime
+        assert_eq!(random_prime(16), Ok(32771));
+        // the scan finds no primitive root modulo the carmichael number 561, which is an error rather than {1}
+        assert_eq!(
+            random_primitive_root(561),
+            Err(SubgroupError::GeneratorNotFound)
+        );
+        assert_eq!(
+            SubgroupBuilder::new(561, 4)
+                .primality_test(|_| true)
+                .build(),
+            Err(SubgroupError::GeneratorNotFound)
+        );
+
+        RNG_DISABLED.with(|disabled| disabled.set(false));
+        assert!(rng_available());
     }
-    // test is_generator function
+    // one node per divisor of p-1 with a generator of that order and the orders it contains
     #[test]
-    fn test_is_generator() {
-        assert_eq!(is_generator(7, 3), true);
-        assert_eq!(is_generator(11, 2), true);
-        assert_eq!(is_generator(13, 2), true);
-        assert_eq!(is_generator(17, 3), true);
-        assert_eq!(is_generator(19, 2), true);
-        assert_eq!(is_generator(23, 5), true);
-        assert_eq!(is_generator(29, 2), true);
-        assert_eq!(is_generator(31, 3), true);
-        assert_eq!(is_generator(37, 2), true);
-        assert_eq!(is_generator(41, 6), true);
-        assert_eq!(is_generator(43, 3), true);
-        assert_eq!(is_generator(47, 5), true);
-        assert_eq!(is_generator(53, 2), true);
-        assert_eq!(is_generator(59, 2), true);
-        assert_eq!(is_generator(61, 2), true);
-        assert_eq!(is_generator(67, 2), true);
-        assert_eq!(is_generator(71, 7), true);
-        assert_eq!(is_generator(73, 5), true);
-        assert_eq!(is_generator(79, 3), true);
-        assert_eq!(is_generator(83, 2), true);
-        assert_eq!(is_generator(89, 3), true);
-        assert_eq!(is_generator(97, 5), true);
-        assert_eq!(is_generator(101, 2), true);
-        assert_eq!(is_generator(103, 5), true);
-        assert_eq!(is_generator(107, 2), true);
-        assert_eq!(is_generator(109, 6), true);
-        assert_eq!(is_generator(113, 3), true);
-        assert_eq!(is_generator(127, 3), true);
-        // assert_eq!(is_generator(337, 85), true);
+    fn test_subgroup_lattice() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::subgroup_lattice;
+        use crate::primitive_root::multiplicative_order;
+        let lattice = subgroup_lattice(13).unwrap();
+        let orders: Vec<u64> = lattice.iter().map(|node| node.order).collect();
+        assert_eq!(orders, vec![1, 2, 3, 4, 6, 12]);
+        assert_eq!(lattice[0].contains, Vec::<u64>::new());
+        assert_eq!(lattice[4].contains, vec![1, 2, 3]);
+        assert_eq!(lattice[5].contains, vec![1, 2, 3, 4, 6]);
+        assert_eq!(lattice[5].generator, 2);
+
+        let p = 61;
+        let lattice = subgroup_lattice(p).unwrap();
+        assert_eq!(lattice.len(), 12);
+        for node in &lattice {
+            assert_eq!(multiplicative_order(node.generator, p), Some(node.order));
+            for other in &lattice {
+                let edge = node.contains.contains(&other.order);
+                assert_eq!(
+                    edge,
+                    other.order != node.order && node.order % other.order == 0
+                );
+                // a contained subgroup is generated by a power of the larger generator
+                if edge {
+                    let inner = mod_exp(node.generator, node.order / other.order, p);
+                    assert_eq!(inner, other.generator);
+                }
+            }
+        }
+        assert_eq!(subgroup_lattice(2).unwrap()[0].contains, Vec::<u64>::new());
+        assert_eq!(subgroup_lattice(12), Err(SubgroupError::NotPrime));
     }
-    // test miller rabin working correctly so test is prime
+    // checked_mod_mul agrees with mod_mul when the product fits in u64 and is None when it does not
     #[test]
-    fn test_is_prime() {
-        assert_eq!(crate::sanity_checks::is_prime(7, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(11, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(13, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(17, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(19, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(23, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(29, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(31, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(37, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(41, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(43, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(47, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(53, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(59, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(61, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(67, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(71, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(73, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(79, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(83, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(89, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(97, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(101, 5), true);
-        assert_eq!(crate::sanity_checks::is_prime(103, 5), true);
+    fn test_checked_mod_mul() {
+        use crate::sanity_checks::{checked_mod_mul, mod_mul};
+        assert_eq!(checked_mod_mul(6, 7, 10), Some(2));
+        assert_eq!(
+            checked_mod_mul(1 << 32, (1 << 32) - 1, u64::MAX),
+            Some(mod_mul(1 << 32, (1 << 32) - 1, u64::MAX))
+        );
+        // both factors close to a 62 bit modulus, the product needs about 124 bits
+        let p = 4611686018427387847;
+        assert_eq!(checked_mod_mul(p - 1, p - 1, p), None);
+        assert_eq!(checked_mod_mul(1 << 32, 1 << 32, p), None);
+        assert_eq!(mod_mul(p - 1, p - 1, p), 1);
+        // below 2^32 the naive path is always fine
+        let q = 4294967291;
+        assert_eq!(checked_mod_mul(q - 1, q - 1, q), Some(1));
     }
-    // test the multiplicative subgroup
-    // asserts that for functions with n not a foctor of p - 1 returns error
+    // the representatives pick out every other coset of the subgroup exactly once
     #[test]
-    fn test_multiplicative_subgroup() {
-        assert_eq!(multiplicative_subgroup(7, 3).unwrap(), vec![1, 2, 4]);
-        // assert_eq!(multiplicative_subgroup(11, 5).unwrap(), vec![1, 3, 4, 5, 9]);
+    fn test_subgroup_and_coset_reps() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::{coset_representative, subgroup_and_coset_reps};
+        use std::collections::HashSet;
+        let p = 97;
+        for n in [1, 2, 8, 12, 32, 96] {
+            let (subgroup, reps) = subgroup_and_coset_reps(p, n).unwrap();
+            assert_eq!(subgroup.len() as u64, n);
+            assert_eq!(reps.len() as u64, (p - 1) / n - 1);
+            // the subgroup and every representative land in different cosets
+            let cosets: HashSet<u64> = std::iter::once(1)
+                .chain(reps.iter().copied())
+                .map(|x| coset_representative(x, p, n).unwrap())
+                .collect();
+            assert_eq!(cosets.len() as u64, (p - 1) / n);
+            assert!(reps.iter().all(|&x| mod_exp(x, n, p) != 1));
+        }
+        assert_eq!(
+            subgroup_and_coset_reps(97, 5),
+            Err(SubgroupError::NotFactor)
+        );
+        assert_eq!(subgroup_and_coset_reps(2, 1), Ok((vec![1], vec![])));
+    }
+    // a coset has the size of the subgroup and is disjoint from it unless c is in the subgroup
+    #[test]
+    fn test_coset() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::{coset, is_in_subgroup};
+        use std::collections::HashSet;
+        let p = 97;
+        let n = 8;
+        let subgroup: HashSet<u64> = multiplicative_subgroup(p, n).unwrap().into_iter().collect();
+        // 5 is a primitive root so it is not in the subgroup of size 8
+        assert!(!is_in_subgroup(5, p, n));
+        let shifted = coset(p, n, 5).unwrap();
+        assert_eq!(shifted.len(), 8);
+        assert_eq!(shifted[0], 5);
+        let shifted: HashSet<u64> = shifted.into_iter().collect();
+        assert_eq!(shifted.len(), 8);
+        assert!(shifted.is_disjoint(&subgroup));
+        // shifting by an element of the subgroup gives the subgroup back
+        let inside = *subgroup.iter().find(|&&x| x != 1).unwrap();
+        let same: HashSet<u64> = coset(p, n, inside).unwrap().into_iter().collect();
+        assert_eq!(same, subgroup);
+        // c is only used modulo p
+        assert!(subgroups_equal(
+            &coset(p, n, 5 + p).unwrap(),
+            &coset(p, n, 5).unwrap(),
+            p
+        ));
+        assert_eq!(coset(p, n, 0), Err(SubgroupError::ZeroElement));
+        assert_eq!(coset(p, n, 2 * p), Err(SubgroupError::ZeroElement));
+        assert_eq!(coset(p, 7, 5), Err(SubgroupError::NotFactor));
+    }
+    // validate passes real subgroups and reports every problem with a corrupted one
+    #[test]
+    fn test_subgroup_validate() {
+        use crate::multiplicative_subgruop::Subgroup;
+        assert_eq!(Subgroup::new(97, 12).unwrap().validate(), Ok(()));
+        assert_eq!(Subgroup::new(2, 1).unwrap().validate(), Ok(()));
+        assert_eq!(
+            Subgroup::from_raw_parts(13, 4, 5, vec![1, 5, 12, 8]).validate(),
+            Ok(())
+        );
+
+        // 1 replaced by a repeated 5, the list is one short and 3 does not have order 4
+        let corrupted = Subgroup::from_raw_parts(13, 4, 3, vec![5, 5, 12]);
+        let problems = corrupted.validate().unwrap_err();
+        assert_eq!(
+            problems,
+            vec![
+                "1 is not an element",
+                "there are 3 elements instead of n = 4",
+                "5 appears 2 times",
+                "5 * 12 = 8 mod 13 is not an element",
+                "the generator 3 does not have order 4",
+            ]
+        );
+        // only closure fails
+        let problems = Subgroup::from_raw_parts(13, 4, 5, vec![1, 5, 12, 7])
+            .validate()
+            .unwrap_err();
+        assert_eq!(problems, vec!["5 * 12 = 8 mod 13 is not an element"]);
+    }
+    // the discrete log inside the subgroup matches the position in power order
+    #[test]
+    fn test_discrete_log_subgroup() {
+        use crate::multiplicative_subgruop::{discrete_log_subgroup, SubgroupContext};
+        // checked against every power for small smooth orders
+        let p = 97;
+        for n in [1, 2, 12, 16, 32, 48, 96] {
+            let subgroup = multiplicative_subgroup(p, n).unwrap();
+            let h = if n == 1 { 1 } else { subgroup[1] };
+            for (i, &x) in subgroup.iter().enumerate() {
+                assert_eq!(discrete_log_subgroup(h, x, p, n), Some(i as u64));
+            }
+        }
+        // 3 has order 48 so it is not in the subgroup of size 32
+        let h = multiplicative_subgroup(p, 32).unwrap()[1];
+        assert_eq!(discrete_log_subgroup(h, 3, p, 32), None);
+        assert_eq!(discrete_log_subgroup(h, 0, p, 32), None);
+
+        // order 2^23 * 7 * 17, far too large for a plain scan
+        let p = 998244353;
+        let context = SubgroupContext::new(p, p - 1).unwrap();
+        for i in [0, 1, 5, 119, 1 << 23, 998244351, 123456789] {
+            let x = context.element_at(i);
+            assert_eq!(
+                discrete_log_subgroup(context.generator(), x, p, p - 1),
+                Some(i)
+            );
+        }
+    }
+    // the sage and magma literals for a subgroup
+    #[test]
+    fn test_to_sage_and_magma() {
+        use crate::multiplicative_subgruop::{to_magma, to_sage};
+        let result = SubgroupResult {
+            prime: 7,
+            order: 3,
+            generator: 2,
+            elements: vec![1, 2, 4],
+        };
+        assert_eq!(to_sage(&result), "[1, 2, 4]");
+        assert_eq!(to_magma(&result), "{ 1, 2, 4 }");
+        let trivial = SubgroupResult {
+            prime: 7,
+            order: 1,
+            generator: 1,
+            elements: vec![1],
+        };
+        assert_eq!(to_sage(&trivial), "[1]");
+        assert_eq!(to_magma(&trivial), "{ 1 }");
+    }
+    // one csv row per subgroup after the header, the elements separated by semicolons
+    #[test]
+    fn test_write_subgroups_csv() {
+        use crate::multiplicative_subgruop::write_subgroups_csv;
+        let rows = [
+            SubgroupResult {
+                prime: 7,
+                order: 3,
+                generator: 2,
+                elements: vec![1, 2, 4],
+            },
+            SubgroupResult {
+                prime: 13,
+                order: 1,
+                generator: 1,
+                elements: vec![1],
+            },
+        ];
+        let mut out = Vec::new();
+        write_subgroups_csv(&rows, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            vec!["prime,order,generator,elements", "7,3,2,1;2;4", "13,1,1,1"]
+        );
+
+        let mut out = Vec::new();
+        write_subgroups_csv(&[], &mut out).unwrap();
+        assert_eq!(out, b"prime,order,generator,elements\n");
+    }
+    // korselt's criterion gives exactly the carmichael numbers below 10000
+    #[test]
+    fn test_is_carmichael() {
+        use crate::primitive_root::is_carmichael;
+        assert_eq!(is_carmichael(561), true);
+        assert_eq!(is_carmichael(41), false);
+        let carmichael: Vec<u64> = (1..10000).filter(|&n| is_carmichael(n)).collect();
+        assert_eq!(carmichael, vec![561, 1105, 1729, 2465, 2821, 6601, 8911]);
+        // squarefree and passing the divisibility check for some but not all primes
+        assert_eq!(is_carmichael(15), false);
+        // not squarefree
+        assert_eq!(is_carmichael(45), false);
+        assert_eq!(is_carmichael(0), false);
+        assert_eq!(is_carmichael(1), false);
+        // 561 fools fermat with every coprime base, which is why the crate uses miller rabin
+        for a in (2..561).filter(|&a| crate::sanity_checks::gcd(a, 561) == 1) {
+            assert_eq!(mod_exp(a, 560, 561), 1);
+        }
+        assert_eq!(crate::sanity_checks::is_prime_deterministic(561), false);
+    }
+    // the capped search gives up with GeneratorNotFound and is reproducible with a seeded rng
+    #[test]
+    fn test_multiplicative_subgroup_try() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::{
+            multiplicative_subgroup_try, multiplicative_subgroup_try_with_rng,
+        };
+        use rand::rngs::mock::StepRng;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        // a generator that only ever returns 0 makes every candidate 2, which has order 3 modulo 7
+        let mut stuck = StepRng::new(0, 0);
+        for max_attempts in [0, 1, 100] {
+            assert_eq!(
+                multiplicative_subgroup_try_with_rng(7, 3, max_attempts, &mut stuck),
+                Err(SubgroupError::GeneratorNotFound)
+            );
+        }
+        // 2311 - 1 = 2 * 3 * 5 * 7 * 11 so only 480 of the 2310 elements are primitive roots
+        let mut rng = StdRng::seed_from_u64(1);
+        let subgroup = multiplicative_subgroup_try_with_rng(2311, 10, 1000, &mut rng).unwrap();
+        assert!(subgroups_equal(
+            &subgroup,
+            &multiplicative_subgroup_deterministic(2311, 10).unwrap(),
+            2311
+        ));
+        assert!(multiplicative_subgroup_try(2311, 10, 1000).is_ok());
+        assert_eq!(
+            multiplicative_subgroup_try(2311, 10, 0),
+            Err(SubgroupError::GeneratorNotFound)
+        );
+        // nothing is random for p = 2 and p = 3
+        assert_eq!(multiplicative_subgroup_try(3, 2, 0), Ok(vec![1, 2]));
+        assert_eq!(
+            multiplicative_subgroup_try(2311, 4, 1),
+            Err(SubgroupError::NotFactor)
+        );
+    }
+    // subgroups of the units of Z[i]/p are closed and have the requested size
+    #[test]
+    fn test_multiplicative_subgroup_gaussian() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::multiplicative_subgroup_gaussian;
+        use std::collections::HashSet;
+        // modulo 3 the fourth roots of unity are exactly 1, i, -1, -i
+        let roots: HashSet<(u64, u64)> = multiplicative_subgroup_gaussian(3, 4)
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(roots, HashSet::from([(1, 0), (0, 1), (2, 0), (0, 2)]));
+        // the units of Z[i]/3 are cyclic of order 8, so a subgroup of size 8 is all of them
+        let units = multiplicative_subgroup_gaussian(3, 8).unwrap();
+        assert_eq!(units[0], (1, 0));
+        assert_eq!(units.iter().collect::<HashSet<_>>().len(), 8);
+        assert!(!units.contains(&(0, 0)));
+        // 7^2 - 1 = 48, orders dividing 48 but not 6 need the imaginary part
+        let subgroup = multiplicative_subgroup_gaussian(7, 16).unwrap();
+        assert_eq!(subgroup.iter().collect::<HashSet<_>>().len(), 16);
+        assert!(subgroup.iter().any(|&(_, imag)| imag != 0));
+        assert_eq!(
+            multiplicative_subgroup_gaussian(7, 5),
+            Err(SubgroupError::NotFactor)
+        );
+        // modulo 5 every unit has order dividing 4 even though there are 16 units
+        assert_eq!(multiplicative_subgroup_gaussian(5, 4).unwrap().len(), 4);
+        assert_eq!(
+            multiplicative_subgroup_gaussian(5, 8),
+            Err(SubgroupError::NotFactor)
+        );
+        assert_eq!(
+            multiplicative_subgroup_gaussian(2, 2).unwrap(),
+            vec![(1, 0), (0, 1)]
+        );
+        assert_eq!(
+            multiplicative_subgroup_gaussian(9, 2),
+            Err(SubgroupError::NotPrime)
+        );
+        // a p above 2^32 so p^2 - 1 does not fit in u64
+        let p = 4294967311;
+        assert_eq!(p % 4, 3);
+        let subgroup = multiplicative_subgroup_gaussian(p, 32 * 131).unwrap();
+        assert_eq!(subgroup.iter().collect::<HashSet<_>>().len(), 32 * 131);
+        assert_eq!(
+            multiplicative_subgroup_gaussian(p, 64),
+            Err(SubgroupError::NotFactor)
+        );
+    }
+    // the subgroup without 1, still in power order
+    #[test]
+    fn test_multiplicative_subgroup_nontrivial() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::multiplicative_subgroup_nontrivial;
+        let p = 97;
+        for n in [1, 2, 3, 12, 96] {
+            let nontrivial = multiplicative_subgroup_nontrivial(p, n).unwrap();
+            assert_eq!(nontrivial.len() as u64, n - 1);
+            assert!(!nontrivial.contains(&1));
+            // still in power order, every element is the previous one times h = nontrivial[0]
+            for pair in nontrivial.windows(2) {
+                assert_eq!(
+                    pair[1],
+                    crate::sanity_checks::mod_mul(pair[0], nontrivial[0], p)
+                );
+            }
+            let mut full = multiplicative_subgroup(p, n).unwrap();
+            full.retain(|&x| x != 1);
+            assert!(subgroups_equal(&nontrivial, &full, p));
+        }
+        assert_eq!(multiplicative_subgroup_nontrivial(p, 1), Ok(vec![]));
+        assert_eq!(
+            multiplicative_subgroup_nontrivial(p, 5),
+            Err(SubgroupError::NotFactor)
+        );
+    }
+    // subgroups compare and hash by their elements, not by the order they are listed in
+    #[test]
+    fn test_subgroup_hash_and_eq() {
+        use crate::multiplicative_subgruop::Subgroup;
+        use std::collections::HashSet;
+        let mut cache = HashSet::new();
+        // the generator is random so these usually list the elements in different orders
+        for _ in 0..5 {
+            cache.insert(Subgroup::new(97, 12).unwrap());
+        }
+        cache.insert(Subgroup::new(97, 8).unwrap());
+        cache.insert(Subgroup::new(193, 12).unwrap());
+        assert_eq!(cache.len(), 3);
+        assert!(cache.contains(&Subgroup::new(97, 12).unwrap()));
+
+        // only the set of elements counts, not the generator or the order
+        let a = Subgroup::from_raw_parts(13, 4, 5, vec![1, 5, 12, 8]);
+        let b = Subgroup::from_raw_parts(13, 4, 8, vec![1, 8, 12, 5]);
+        assert_eq!(a, b);
+        assert_eq!(HashSet::from([a.clone(), b]).len(), 1);
+        assert_ne!(a, Subgroup::new(13, 3).unwrap());
+        assert_ne!(Subgroup::new(5, 1).unwrap(), Subgroup::new(7, 1).unwrap());
+    }
+    // the cyclotomic polynomials modulo p, whose roots are the elements of order exactly n
+    #[test]
+    fn test_cyclotomic_mod() {
+        use crate::error::SubgroupError;
+        use crate::field::cyclotomic_mod;
+        use crate::primitive_root::{euler_totient, mobius, multiplicative_order};
+        assert_eq!(
+            (1..=12).map(mobius).collect::<Vec<i8>>(),
+            vec![1, -1, -1, 0, -1, 1, -1, 0, 0, 1, -1, 0]
+        );
+        let p = 97;
+        // x - 1, x + 1, x^2 + x + 1 and x^4 - x^2 + 1
+        assert_eq!(cyclotomic_mod(1, p), Ok(vec![96, 1]));
+        assert_eq!(cyclotomic_mod(2, p), Ok(vec![1, 1]));
+        assert_eq!(cyclotomic_mod(3, p), Ok(vec![1, 1, 1]));
+        assert_eq!(cyclotomic_mod(12, p), Ok(vec![1, 0, 96, 0, 1]));
+        let evaluate = |poly: &[u64], x: u64| {
+            poly.iter().rev().fold(0, |acc, &c| {
+                (crate::sanity_checks::mod_mul(acc, x, p) + c) % p
+            })
+        };
+        for n in [4, 6, 8, 12, 16, 24, 32, 48, 96] {
+            let poly = cyclotomic_mod(n, p).unwrap();
+            assert_eq!(poly.len() as u64 - 1, euler_totient(n));
+            assert_eq!(poly.last(), Some(&1));
+            // zero exactly at the elements of order n
+            for x in 1..p {
+                assert_eq!(
+                    evaluate(&poly, x) == 0,
+                    multiplicative_order(x, p) == Some(n)
+                );
+            }
+        }
+        // the coefficients of phi_105 include a -2, the first that is not 0 or +-1
+        assert!(cyclotomic_mod(105, p).unwrap().contains(&95));
+        assert_eq!(cyclotomic_mod(0, p), Err(SubgroupError::NotFactor));
+        assert_eq!(cyclotomic_mod(4, 91), Err(SubgroupError::NotPrime));
+        // the largest prime below 2^64, the sums of two coefficients do not fit in u64
+        let big = 18446744073709551557;
+        assert_eq!(cyclotomic_mod(1, big), Ok(vec![big - 1, 1]));
+        assert_eq!(cyclotomic_mod(12, big), Ok(vec![1, 0, big - 1, 0, 1]));
+        let phi_105 = cyclotomic_mod(105, big).unwrap();
+        assert_eq!(phi_105.len(), 49);
+        assert!(phi_105.contains(&(big - 2)));
+        for n in 1..400 {
+            assert_eq!(
+                cyclotomic_mod(n, big).unwrap().len() as u64 - 1,
+                euler_totient(n)
+            );
+        }
+    }
+    // the number of elements of each order matches counting them one at a time
+    #[test]
+    fn test_order_histogram() {
+        use crate::error::SubgroupError;
+        use crate::field::order_histogram;
+        use crate::primitive_root::multiplicative_order;
+        use std::collections::BTreeMap;
+        for p in [2, 3, 13, 97, 101] {
+            let histogram = order_histogram(p).unwrap();
+            assert_eq!(histogram.values().sum::<u64>(), p - 1);
+            // the same counts by computing the order of every element
+            let mut counted = BTreeMap::new();
+            for x in 1..p {
+                *counted
+                    .entry(multiplicative_order(x, p).unwrap())
+                    .or_insert(0) += 1;
+            }
+            assert_eq!(histogram, counted);
+        }
+        let histogram = order_histogram(13).unwrap();
+        assert_eq!(
+            histogram.into_iter().collect::<Vec<_>>(),
+            vec![(1, 1), (2, 1), (3, 2), (4, 2), (6, 2), (12, 4)]
+        );
+        assert_eq!(order_histogram(1), Err(SubgroupError::NotPrime));
+    }
+    // a cyclic group has one subgroup per divisor, a non cyclic group can have several
+    #[test]
+    fn test_unique_subgroup_of_order() {
+        use crate::error::SubgroupError;
+        use crate::field::unique_subgroup_of_order;
+        use crate::sanity_checks::{gcd, mod_mul};
+        use std::collections::BTreeSet;
+        // a prime modulus gives a cyclic group with one subgroup for each divisor
+        for n in [1, 2, 3, 4, 6, 12] {
+            assert_eq!(unique_subgroup_of_order(13, n), Ok(true));
+        }
+        // the group modulo 15 is C2 x C4
+        assert_eq!(unique_subgroup_of_order(15, 2), Ok(false));
+        assert_eq!(unique_subgroup_of_order(15, 4), Ok(false));
+        assert_eq!(unique_subgroup_of_order(15, 8), Ok(true));
+        assert_eq!(
+            unique_subgroup_of_order(15, 3),
+            Err(SubgroupError::NotFactor)
+        );
+        assert_eq!(
+            unique_subgroup_of_order(0, 1),
+            Err(SubgroupError::NotFactor)
+        );
+
+        // every subgroup of these groups is generated by two elements, so count them by brute force
+        for m in [8, 15, 16, 21, 35, 45] {
+            let units: Vec<u64> = (1..m).filter(|&x| gcd(x, m) == 1).collect();
+            let mut subgroups = BTreeSet::new();
+            for &x in &units {
+                for &y in &units {
+                    let mut elements = BTreeSet::from([1]);
+                    loop {
+                        let next: BTreeSet<u64> = elements
+                            .iter()
+                            .flat_map(|&e| [mod_mul(e, x, m), mod_mul(e, y, m)])
+                            .chain(elements.iter().copied())
+                            .collect();
+                        if next.len() == elements.len() {
+                            break;
+                        }
+                        elements = next;
+                    }
+                    subgroups.insert(elements);
+                }
+            }
+            for n in (1..=units.len() as u64).filter(|n| units.len() as u64 % n == 0) {
+                let count = subgroups.iter().filter(|s| s.len() as u64 == n).count();
+                assert_eq!(
+                    unique_subgroup_of_order(m, n),
+                    Ok(count == 1),
+                    "m = {} n = {}",
+                    m,
+                    n
+                );
+            }
+        }
+    }
+    // square and multiply takes one squaring per bit and one multiplication per set bit
+    #[test]
+    fn test_mod_exp_counted() {
+        use crate::sanity_checks::mod_exp_counted;
+        let p = 998244353;
+        for s in [0, 1, 2, 3, 255, 256, 998244352, u64::MAX] {
+            let (result, count) = mod_exp_counted(3, s, p);
+            assert_eq!(result, mod_exp(3, s, p));
+            let bits = 64 - s.leading_zeros();
+            assert!(count <= 2 * bits);
+            assert_eq!(count, bits + s.count_ones());
+        }
+        // 2^20 has 21 bits and a single set bit
+        assert_eq!(mod_exp_counted(3, 1 << 20, p).1, 22);
+        assert_eq!(mod_exp_counted(3, 0, p), (1, 0));
+    }
+    // a Subgroup can be passed where a slice or a Vec of its elements is expected
+    #[test]
+    fn test_subgroup_as_ref_and_into_vec() {
+        use crate::multiplicative_subgruop::Subgroup;
+        fn product_of(elements: impl AsRef<[u64]>, p: u64) -> u64 {
+            elements
+                .as_ref()
+                .iter()
+                .fold(1, |acc, &x| crate::sanity_checks::mod_mul(acc, x, p))
+        }
+        fn sum_of(elements: &[u64]) -> u64 {
+            elements.iter().sum()
+        }
+        let subgroup = Subgroup::new(13, 4).unwrap();
+        // 1 * 5 * 12 * 8 = 480 = -1 mod 13
+        assert_eq!(product_of(&subgroup, 13), 12);
+        assert_eq!(sum_of(subgroup.as_ref()), 26);
+        let elements = subgroup.to_vec();
+        let vec: Vec<u64> = subgroup.into();
+        assert_eq!(vec, elements);
+        assert_eq!(product_of(vec, 13), 12);
+    }
+    // the ntt prime closest to the target, checked against every prime in a window
+    #[test]
+    fn test_nearest_ntt_prime() {
+        use crate::ntt::{nearest_ntt_prime, two_adicity};
+        // 7340033 = 7 * 2^20 + 1
+        assert_eq!(nearest_ntt_prime(7340033, 20), Some(7340033));
+        assert_eq!(nearest_ntt_prime(7000000, 20), Some(7340033));
+        // 1107296257 = 132 * 2^23 + 1 is 33 million above 2^30, 998244353 is 75 million below
+        assert_eq!(nearest_ntt_prime(1 << 30, 23), Some(1107296257));
+        assert_eq!(nearest_ntt_prime(1 << 29, 23), Some(595591169));
+        // the closest from either side, checked against every prime in the window
+        for target in [0, 1, 2, 3, 10, 100, 1000, 5000, 40000] {
+            for k in [0, 1, 2, 4, 6] {
+                let p = nearest_ntt_prime(target, k).unwrap();
+                assert!(crate::sanity_checks::is_prime_deterministic(p));
+                assert!(two_adicity(p - 1) >= k);
+                let distance = p.abs_diff(target);
+                let window = target.saturating_sub(distance)..=target + distance;
+                for q in window.filter(|&q| {
+                    crate::sanity_checks::is_prime_deterministic(q) && two_adicity(q - 1) >= k
+                }) {
+                    let d = q.abs_diff(target);
+                    assert!(
+                        d > distance || (d == distance && q >= p),
+                        "target {} k {}",
+                        target,
+                        k
+                    );
+                }
+            }
+        }
+        assert_eq!(nearest_ntt_prime(100, 64), None);
+    }
+    // the subgroup generated by a has the order of a as its size
+    #[test]
+    fn test_generated_subgroup() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::generated_subgroup;
+        use crate::primitive_root::multiplicative_order;
+        assert_eq!(generated_subgroup(2, 7), Ok(vec![1, 2, 4]));
+        assert_eq!(generated_subgroup(3, 7), Ok(vec![1, 3, 2, 6, 4, 5]));
+        assert_eq!(generated_subgroup(1, 7), Ok(vec![1]));
+        assert_eq!(generated_subgroup(9, 7), Ok(vec![1, 2, 4]));
+        for a in 1..97 {
+            let subgroup = generated_subgroup(a, 97).unwrap();
+            assert_eq!(Some(subgroup.len() as u64), multiplicative_order(a, 97));
+            assert!(subgroups_equal(
+                &subgroup,
+                &multiplicative_subgroup(97, subgroup.len() as u64).unwrap(),
+                97
+            ));
+        }
+        assert_eq!(generated_subgroup(14, 7), Err(SubgroupError::ZeroElement));
+        assert_eq!(generated_subgroup(2, 9), Err(SubgroupError::NotPrime));
+    }
+    // the three base test below 4759123141 rejects the known strong pseudoprimes
+    #[test]
+    fn test_is_prime_deterministic_small_bases() {
+        use crate::sanity_checks::{is_prime_bpsw, is_prime_deterministic};
+        // the first composite that is a strong probable prime to the bases 2, 7 and 61
+        assert_eq!(is_prime_deterministic(4_759_123_141), false);
+        assert_eq!(is_prime_deterministic(61), true);
+        // strong pseudoprimes to base 2 and to bases 2 and 3
+        for n in [2047, 3277, 4033, 1373653, 25326001, 3215031751] {
+            assert_eq!(is_prime_deterministic(n), false);
+        }
+        assert_eq!(is_prime_deterministic(4294967291), true);
+        assert_eq!(is_prime_deterministic(4294967311), true);
+        // both sides of the switch between the two base sets and around 2^32 agree with bpsw
+        for centre in [4_759_123_141u64, 1 << 32] {
+            for n in centre - 3000..centre + 3000 {
+                assert_eq!(is_prime_deterministic(n), is_prime_bpsw(n), "n = {}", n);
+            }
+        }
+        for n in 0..10000 {
+            assert_eq!(is_prime_deterministic(n), is_prime_bpsw(n), "n = {}", n);
+        }
+    }
+    // each step of the construction is reported and verified holds for correct steps
+    #[test]
+    fn test_explain_subgroup() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::{explain_subgroup, OrderCheck};
+        // 2 is the smallest primitive root of 13, e = 12 / 6 = 2 and h = 4
+        let explanation = explain_subgroup(13, 6).unwrap();
+        assert_eq!(explanation.prime, 13);
+        assert_eq!(explanation.order, 6);
+        assert_eq!(explanation.primitive_root, 2);
+        assert_eq!(explanation.cofactor, 2);
+        assert_eq!(explanation.subgroup_generator, 4);
+        assert_eq!(explanation.generator_to_order, 1);
+        // 4^3 = 12 and 4^2 = 3
+        assert_eq!(
+            explanation.order_checks,
+            vec![
+                OrderCheck {
+                    prime_factor: 2,
+                    exponent: 3,
+                    value: 12
+                },
+                OrderCheck {
+                    prime_factor: 3,
+                    exponent: 2,
+                    value: 3
+                },
+            ]
+        );
+        assert!(explanation.verified());
+        assert_eq!(
+            multiplicative_subgroup_deterministic(13, 6).unwrap()[1],
+            explanation.subgroup_generator
+        );
+        // the trivial subgroup has nothing to check
+        let trivial = explain_subgroup(13, 1).unwrap();
+        assert_eq!((trivial.cofactor, trivial.subgroup_generator), (12, 1));
+        assert!(trivial.order_checks.is_empty() && trivial.verified());
+        assert_eq!(explain_subgroup(13, 5), Err(SubgroupError::NotFactor));
+        assert_eq!(explain_subgroup(14, 1), Err(SubgroupError::NotPrime));
+    }
+    // the transforms of length 1, 2 and 4 worked out by hand
+    #[test]
+    fn test_ntt_small_lengths() {
+        use crate::error::SubgroupError;
+        use crate::ntt::{intt, ntt, stride_root};
+        let p = 17;
+        // length 1 is the identity
+        let mut data = vec![5];
+        ntt(&mut data, p).unwrap();
+        assert_eq!(data, vec![5]);
+        intt(&mut data, p).unwrap();
+        assert_eq!(data, vec![5]);
+        let mut data = vec![22];
+        ntt(&mut data, p).unwrap();
+        assert_eq!(data, vec![5]);
+        // length 2 is [a + b, a - b]
+        let mut data = vec![3, 5];
+        ntt(&mut data, p).unwrap();
+        assert_eq!(data, vec![8, 15]);
+        intt(&mut data, p).unwrap();
+        assert_eq!(data, vec![3, 5]);
+        let mut data = vec![16, 16];
+        ntt(&mut data, p).unwrap();
+        assert_eq!(data, vec![15, 0]);
+        // longer lengths against the definition
+        let p = 998244353;
+        for n in [4, 8, 64] {
+            let input: Vec<u64> = (0..n).map(|i| (i * i * 7919 + 3) % p).collect();
+            let w = stride_root(p, n, n).unwrap();
+            let expected: Vec<u64> = (0..n)
+                .map(|k| {
+                    input.iter().enumerate().fold(0, |acc, (j, &a)| {
+                        (acc + crate::sanity_checks::mod_mul(a, mod_exp(w, j as u64 * k, p), p)) % p
+                    })
+                })
+                .collect();
+            let mut data = input.clone();
+            ntt(&mut data, p).unwrap();
+            assert_eq!(data, expected);
+            intt(&mut data, p).unwrap();
+            assert_eq!(data, input);
+        }
+        assert_eq!(ntt(&mut [1, 2, 3], p), Err(SubgroupError::NotPowerOfTwo));
+        assert_eq!(ntt(&mut [], p), Err(SubgroupError::NotPowerOfTwo));
+        // 32 does not divide 17 - 1
+        assert_eq!(ntt(&mut [0; 32], 17), Err(SubgroupError::NotFactor));
+        assert_eq!(ntt(&mut [1, 2], 2), Err(SubgroupError::NotFactor));
+        assert_eq!(ntt(&mut [1], 15), Err(SubgroupError::NotPrime));
+    }
+    // a plan reused for several inputs gives the same output as the standalone transform
+    #[test]
+    fn test_ntt_plan() {
+        use crate::error::SubgroupError;
+        use crate::ntt::{intt, ntt, NttPlan};
+        let p = 998244353;
+        for n in [1u64, 2, 4, 16, 1024] {
+            let plan = NttPlan::new(p, n).unwrap();
+            assert_eq!((plan.p(), plan.n()), (p, n));
+            // the same plan used for several inputs
+            for seed in 1..4 {
+                let input: Vec<u64> = (0..n).map(|i| (i * 31337 + seed * 271828) % p).collect();
+                let mut standalone = input.clone();
+                ntt(&mut standalone, p).unwrap();
+                let mut planned = input.clone();
+                plan.forward(&mut planned).unwrap();
+                assert_eq!(planned, standalone);
+                plan.inverse(&mut planned).unwrap();
+                assert_eq!(planned, input);
+                intt(&mut standalone, p).unwrap();
+                assert_eq!(standalone, input);
+            }
+        }
+        let plan = NttPlan::new(p, 8).unwrap();
+        assert_eq!(
+            plan.forward(&mut [0; 4]),
+            Err(SubgroupError::LengthMismatch)
+        );
+        assert_eq!(
+            plan.inverse(&mut [0; 16]),
+            Err(SubgroupError::LengthMismatch)
+        );
+        assert_eq!(NttPlan::new(p, 6), Err(SubgroupError::NotPowerOfTwo));
+        assert_eq!(NttPlan::new(p, 1 << 24), Err(SubgroupError::NotFactor));
+    }
+    // random elements are in the subgroup and every element turns up about equally often
+    #[test]
+    fn test_random_subgroup_element() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::{is_in_subgroup, random_subgroup_element};
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        use std::collections::HashMap;
+        let mut rng = StdRng::seed_from_u64(178);
+        let (p, n) = (97, 16);
+        let mut counts = HashMap::new();
+        for _ in 0..16000 {
+            let x = random_subgroup_element(p, n, &mut rng).unwrap();
+            assert!(is_in_subgroup(x, p, n));
+            *counts.entry(x).or_insert(0) += 1;
+        }
+        // every element shows up, each about 1000 times
+        assert_eq!(counts.len(), 16);
+        assert!(counts.values().all(|&c| (800..1200).contains(&c)));
+        // the same seed gives the same draws
+        let draws = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..10)
+                .map(|_| random_subgroup_element(p, n, &mut rng).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draws(1), draws(1));
+        assert_eq!(random_subgroup_element(p, 1, &mut rng), Ok(1));
+        assert_eq!(
+            random_subgroup_element(p, 5, &mut rng),
+            Err(SubgroupError::NotFactor)
+        );
+    }
+    // the complement is sorted and splits 1..p with the subgroup
+    #[test]
+    fn test_subgroup_complement() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::subgroup_complement;
+        let p = 97;
+        for n in [1, 2, 12, 48, 96] {
+            let complement = subgroup_complement(p, n).unwrap();
+            assert_eq!(complement.len() as u64, p - 1 - n);
+            assert!(complement.windows(2).all(|w| w[0] < w[1]));
+            let subgroup = multiplicative_subgroup(p, n).unwrap();
+            assert!(subgroup
+                .iter()
+                .all(|x| complement.binary_search(x).is_err()));
+            let mut all = complement.clone();
+            all.extend(subgroup);
+            all.sort_unstable();
+            assert_eq!(all, (1..p).collect::<Vec<u64>>());
+        }
+        assert_eq!(subgroup_complement(7, 3), Ok(vec![3, 5, 6]));
+        assert_eq!(subgroup_complement(7, 4), Err(SubgroupError::NotFactor));
+    }
+    // the lazy divisor iterator yields the same divisors as factors and can stop early
+    #[test]
+    fn test_factors_iter() {
+        use crate::primitive_root::factors_iter;
+        assert_eq!(factors_iter(12).collect::<Vec<_>>(), factors(12));
+        for k in [0, 1, 2, 16, 36, 97, 360, 1001, 65536, 998244352] {
+            assert_eq!(factors_iter(k).collect::<Vec<_>>(), factors(k), "k = {}", k);
+        }
+        // stopping early only looks at the small divisors
+        assert_eq!(factors_iter(u64::MAX - 1).nth(1), Some(2));
+        assert_eq!(factors_iter(360).find(|&d| d > 20), Some(24));
+        assert_eq!(factors_iter(360).take_while(|&d| d < 6).count(), 5);
+    }
+    // subgroup_size checks the parameters and the builder refuses subgroups over the limit
+    #[test]
+    fn test_subgroup_size_and_limit() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::{subgroup_size, SubgroupBuilder};
+        assert_eq!(subgroup_size(97, 12), Ok(12));
+        assert_eq!(subgroup_size(998244353, 1 << 23), Ok(1 << 23));
+        assert_eq!(subgroup_size(97, 5), Err(SubgroupError::NotFactor));
+        assert_eq!(subgroup_size(91, 5), Err(SubgroupError::NotPrime));
+
+        let built = SubgroupBuilder::new(97, 12)
+            .max_reasonable_size(12)
+            .build()
+            .unwrap();
+        assert_eq!(built.len() as u64, subgroup_size(97, 12).unwrap());
+        // rejected before anything is computed
+        assert_eq!(
+            SubgroupBuilder::new(998244353, 998244352)
+                .max_reasonable_size(1 << 20)
+                .build(),
+            Err(SubgroupError::TooLarge)
+        );
+        // the other checks come first
+        assert_eq!(
+            SubgroupBuilder::new(97, 5).max_reasonable_size(1).build(),
+            Err(SubgroupError::NotFactor)
+        );
+        assert_eq!(SubgroupBuilder::new(97, 96).build().unwrap().len(), 96);
+    }
+    // the share of primes in a range that have g as a primitive root
+    #[test]
+    fn test_primitive_root_density() {
+        use crate::field::primitive_root_density;
+        // 2 is a primitive root modulo 3, 5, 11, 13, 19, 29, 37, 53, 59, 61, 67 and 83, 12 of the 25 primes below 100
+        assert_eq!(primitive_root_density(2, 1, 100), 12.0 / 25.0);
+        assert_eq!(primitive_root_density(2, 3, 13), 4.0 / 5.0);
+        assert_eq!(primitive_root_density(2, 1, 1000), 67.0 / 168.0);
+        // ranges holding just 7, where 3 is a primitive root, and just 3, which 3 divides
+        assert_eq!(primitive_root_density(3, 7, 7), 1.0);
+        assert_eq!(primitive_root_density(3, 3, 3), 0.0);
+        assert_eq!(primitive_root_density(2, 24, 28), 0.0);
+        assert_eq!(primitive_root_density(2, 100, 1), 0.0);
+    }
+    // the two ways of taking the elements out of a Subgroup
+    #[test]
+    fn test_subgroup_into_sorted_and_power_ordered_vec() {
+        use crate::multiplicative_subgruop::Subgroup;
+        let subgroup = Subgroup::new(97, 12).unwrap();
+        let g = subgroup.generator();
+        let power_ordered = subgroup.clone().into_power_ordered_vec();
+        let sorted = subgroup.into_sorted_vec();
+        assert_eq!(power_ordered[0], 1);
+        assert_eq!(power_ordered[1], g);
+        assert_eq!(power_ordered[2], mod_exp(g, 2, 97));
+        assert!(sorted.windows(2).all(|w| w[0] < w[1]));
+        assert!(subgroups_equal(&sorted, &power_ordered, 97));
+        // -1 = 96 is g^6 in the middle of the power order but the largest element when sorted
+        assert_ne!(sorted, power_ordered);
+        let known = Subgroup::from_raw_parts(13, 4, 5, vec![1, 5, 12, 8]);
+        assert_eq!(known.clone().into_sorted_vec(), vec![1, 5, 8, 12]);
+        assert_eq!(known.into_power_ordered_vec(), vec![1, 5, 12, 8]);
+    }
+    // one exponent applied to many bases agrees with mod_exp for each base
+    #[test]
+    fn test_mod_exp_multibase() {
+        use crate::sanity_checks::mod_exp_multibase;
+        let p = 998244353;
+        let bases: Vec<u64> = vec![0, 1, 2, 3, p - 1, p, p + 5, 123456789, u64::MAX];
+        for s in [0, 1, 2, 7, 1 << 40, p - 1, u64::MAX] {
+            let expected: Vec<u64> = bases.iter().map(|&b| mod_exp(b, s, p)).collect();
+            assert_eq!(mod_exp_multibase(&bases, s, p), expected, "s = {}", s);
+        }
+        assert_eq!(mod_exp_multibase(&[], 5, p), Vec::<u64>::new());
+        assert_eq!(mod_exp_multibase(&[2, 3], 10, 1000), vec![24, 49]);
+    }
+    // schnorr primes and random schnorr parameters with a generator of order q
+    #[test]
+    fn test_schnorr_params() {
+        use crate::multiplicative_subgruop::{generate_schnorr_params, is_schnorr_prime};
+        use crate::primitive_root::multiplicative_order;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        // 23 = 11 * 2 + 1 and 1019 = 509 * 2 + 1
+        assert!(is_schnorr_prime(23, 11));
+        assert!(is_schnorr_prime(1019, 509));
+        assert!(is_schnorr_prime(998244353, 17));
+        assert!(!is_schnorr_prime(23, 5));
+        assert!(!is_schnorr_prime(25, 3));
+        assert!(!is_schnorr_prime(29, 14));
+
+        let mut rng = StdRng::seed_from_u64(185);
+        for (q_bits, p_bits) in [(2, 4), (8, 16), (16, 32), (20, 40), (32, 63)] {
+            let (p, q, g) = generate_schnorr_params(q_bits, p_bits, &mut rng).unwrap();
+            assert!(is_schnorr_prime(p, q));
+            assert_eq!(64 - q.leading_zeros(), q_bits);
+            assert_eq!(64 - p.leading_zeros(), p_bits);
+            assert_eq!(multiplicative_order(g, p), Some(q));
+        }
+        assert_eq!(generate_schnorr_params(16, 16, &mut rng), None);
+        assert_eq!(generate_schnorr_params(1, 16, &mut rng), None);
+        assert_eq!(generate_schnorr_params(16, 64, &mut rng), None);
+    }
+    // lucas' theorem against pascal's triangle, and NotPrime for a composite modulus
+    #[test]
+    fn test_binomial_mod() {
+        use crate::error::SubgroupError;
+        use crate::sanity_checks::{binomial_mod, mod_inverse};
+        assert_eq!(mod_inverse(3, 7), Some(5));
+        assert_eq!(mod_inverse(10, 17), Some(12));
+        assert_eq!(mod_inverse(4, 10), None);
+        assert_eq!(mod_inverse(0, 7), None);
+        assert_eq!(mod_inverse(5, 1), Some(0));
+
+        // pascal's triangle below p
+        let p = 1_000_000_007;
+        assert_eq!(binomial_mod(5, 2, p), Ok(10));
+        assert_eq!(binomial_mod(10, 0, p), Ok(1));
+        assert_eq!(binomial_mod(10, 10, p), Ok(1));
+        assert_eq!(binomial_mod(3, 5, p), Ok(0));
+        assert_eq!(binomial_mod(60, 30, p), Ok(118264581564861424 % p));
+        for n in 1..30 {
+            for k in 1..n {
+                let expected = (binomial_mod(n - 1, k - 1, 13).unwrap()
+                    + binomial_mod(n - 1, k, 13).unwrap())
+                    % 13;
+                assert_eq!(binomial_mod(n, k, 13), Ok(expected));
+            }
+        }
+        // lucas with n > p: 1000 = 2 * 7^3 + 6 * 7^2 + 2 * 7 + 6 and 500 = 1 * 7^3 + 3 * 7^2 + 1 * 7 + 3
+        // so the digits give (2 choose 1)(6 choose 3)(2 choose 1)(6 choose 3) = 2 * 20 * 2 * 20 = 1600 = 4 mod 7
+        assert_eq!(binomial_mod(1000, 500, 7), Ok(4));
+        // 10 = 1 * 7 + 3 and 5 = 0 * 7 + 5, the last digit 5 > 3 makes it 0, and 252 = 36 * 7
+        assert_eq!(binomial_mod(10, 5, 7), Ok(0));
+        assert_eq!(binomial_mod(u64::MAX, 1, 5), Ok(u64::MAX % 5));
+        // only prime moduli are accepted
+        for p in [0, 1, 8, 91] {
+            assert_eq!(binomial_mod(7, 2, p), Err(SubgroupError::NotPrime), "{}", p);
+        }
+    }
+    // the primes up to the limit that have g as a primitive root
+    #[test]
+    fn test_primes_with_primitive_root() {
+        use crate::field::primes_with_primitive_root;
+        assert_eq!(
+            primes_with_primitive_root(2, 100),
+            Ok(vec![3, 5, 11, 13, 19, 29, 37, 53, 59, 61, 67, 83])
+        );
+        // the limit is included
+        assert_eq!(primes_with_primitive_root(2, 83).unwrap().last(), Some(&83));
+        assert_eq!(primes_with_primitive_root(2, 2), Ok(vec![]));
+        assert_eq!(primes_with_primitive_root(3, 0), Ok(vec![]));
+        for g in [2, 3, 5, 10] {
+            let primes = primes_with_primitive_root(g, 5000).unwrap();
+            assert!(primes.iter().all(|&p| is_primitive_root(p, g)));
+            // and nothing was left out
+            let count = (2..=5000)
+                .filter(|&p| {
+                    crate::sanity_checks::is_prime_deterministic(p) && is_primitive_root(p, g)
+                })
+                .count();
+            assert_eq!(primes.len(), count);
+        }
+    }
+    // the hash lookup in contains agrees with the x^n = 1 test
+    #[test]
+    fn test_subgroup_contains() {
+        use crate::multiplicative_subgruop::{is_in_subgroup, Subgroup};
+        let p = 997;
+        for n in [1, 2, 3, 6, 83, 166, 498, 996] {
+            let subgroup = Subgroup::new(p, n).unwrap();
+            for x in 0..2 * p {
+                assert_eq!(
+                    subgroup.contains(x),
+                    is_in_subgroup(x, p, n),
+                    "x = {} n = {}",
+                    x,
+                    n
+                );
+            }
+        }
+        let subgroup = Subgroup::new(13, 4).unwrap();
+        assert!(subgroup.contains(12) && subgroup.contains(25) && !subgroup.contains(0));
+    }
+    // the twiddle table holds w^i at the bit reversed index of i
+    #[test]
+    fn test_twiddles_bit_reversed() {
+        use crate::error::SubgroupError;
+        use crate::ntt::{bit_reverse_permute, stride_root, twiddles_bit_reversed};
+        let p = 998244353;
+        let w = stride_root(p, 8, 8).unwrap();
+        let twiddles = twiddles_bit_reversed(p, 8).unwrap();
+        // index 1 = 001 holds w^4 = 100 and index 3 = 011 holds w^6 = 110
+        assert_eq!(twiddles[0], 1);
+        assert_eq!(twiddles[1], mod_exp(w, 4, p));
+        assert_eq!(twiddles[3], mod_exp(w, 6, p));
+        assert_eq!(twiddles[1], p - 1);
+        for n in [1, 2, 4, 64, 1024] {
+            let mut twiddles = twiddles_bit_reversed(p, n).unwrap();
+            bit_reverse_permute(&mut twiddles).unwrap();
+            let w = stride_root(p, n, n).unwrap();
+            assert_eq!(
+                twiddles,
+                (0..n).map(|i| mod_exp(w, i, p)).collect::<Vec<u64>>()
+            );
+        }
+        assert_eq!(
+            twiddles_bit_reversed(p, 12),
+            Err(SubgroupError::NotPowerOfTwo)
+        );
+        assert_eq!(twiddles_bit_reversed(97, 64), Err(SubgroupError::NotFactor));
+    }
+    // the largest element order is the carmichael function of n
+    #[test]
+    fn test_max_element_order() {
+        use crate::primitive_root::{has_primitive_root, is_cyclic_group, max_element_order};
+        // prime, prime powers, twice a prime power, then 8, 15, 21 and 24 which are not cyclic
+        assert_eq!(max_element_order(97), 96);
+        assert_eq!(max_element_order(81), 54);
+        assert_eq!(max_element_order(50), 20);
+        assert_eq!(max_element_order(8), 2);
+        assert_eq!(max_element_order(15), 4);
+        assert_eq!(max_element_order(21), 6);
+        assert_eq!(max_element_order(24), 2);
+        for modulus in [1, 2, 4, 97, 81, 50, 3u64.pow(20), 2 * 3u64.pow(10)] {
+            assert!(has_primitive_root(modulus), "{}", modulus);
+        }
+        for modulus in [8, 15, 21, 24, 1 << 20, 97 * 89] {
+            assert!(!has_primitive_root(modulus), "{}", modulus);
+        }
+        assert!(!has_primitive_root(0));
+        for modulus in 1..3000 {
+            assert_eq!(
+                has_primitive_root(modulus),
+                is_cyclic_group(modulus),
+                "{}",
+                modulus
+            );
+        }
+    }
+    // every request in a batch succeeds or fails on its own, in the order given
+    #[test]
+    fn test_multiplicative_subgroups_batch() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::multiplicative_subgroups_batch;
+        let requests = [
+            (97, 12),
+            (15, 2),
+            (13, 5),
+            (998244353, 1 << 10),
+            (2, 1),
+            (13, 0),
+        ];
+        let results = multiplicative_subgroups_batch(&requests);
+        assert_eq!(results.len(), requests.len());
+        assert!(subgroups_equal(
+            results[0].as_ref().unwrap(),
+            &multiplicative_subgroup(97, 12).unwrap(),
+            97
+        ));
+        assert_eq!(results[1], Err(SubgroupError::NotPrime));
+        assert_eq!(results[2], Err(SubgroupError::NotFactor));
+        assert_eq!(results[3].as_ref().unwrap().len(), 1 << 10);
+        assert_eq!(results[4], Ok(vec![1]));
+        assert_eq!(results[5], Err(SubgroupError::NotFactor));
+        // order is kept for a longer list as well
+        let requests: Vec<(u64, u64)> = (1..=96).map(|n| (97, n)).collect();
+        for (n, result) in (1..=96).zip(multiplicative_subgroups_batch(&requests)) {
+            match result {
+                Ok(subgroup) => assert_eq!(subgroup.len() as u64, n),
+                Err(e) => assert!(96 % n != 0 && e == SubgroupError::NotFactor),
+            }
+        }
+    }
+    // both root tables are bit reversed and a butterfly using them round trips
+    #[test]
+    fn test_ntt_roots() {
+        use crate::error::SubgroupError;
+        use crate::ntt::{bit_reverse_permute, ntt, ntt_roots, twiddles_bit_reversed};
+        use crate::sanity_checks::mod_mul;
+        let p = 998244353;
+        let roots = ntt_roots(p, 16).unwrap();
+        assert_eq!(roots.forward.len(), 16);
+        assert_eq!(roots.inverse.len(), 16);
+        assert_eq!(roots.forward[0], 1);
+        assert_eq!(roots.forward[1] * roots.inverse[1] % p, 1);
+        for (f, i) in roots.forward.iter().zip(&roots.inverse) {
+            assert_eq!(*f as u128 * *i as u128 % p as u128, 1);
+        }
+        assert_eq!(16 * roots.n_inverse % p, 1);
+        assert_eq!(roots.forward, twiddles_bit_reversed(p, 16).unwrap());
+        // one radix 2 butterfly loop reading w^e at the bit reversal of e, run with either table
+        let transform = |data: &mut [u64], table: &[u64]| {
+            let bits = data.len().trailing_zeros();
+            let power = |e: usize| table[e.reverse_bits() >> (usize::BITS - bits)];
+            bit_reverse_permute(data).unwrap();
+            let mut len = 2;
+            while len <= data.len() {
+                let stride = data.len() / len;
+                for block in data.chunks_exact_mut(len) {
+                    let (low, high) = block.split_at_mut(len / 2);
+                    for (j, (u, v)) in low.iter_mut().zip(high.iter_mut()).enumerate() {
+                        let t = mod_mul(*v, power(j * stride), p);
+                        (*u, *v) = ((*u + t) % p, (*u + p - t) % p);
+                    }
+                }
+                len *= 2;
+            }
+        };
+        let data: Vec<u64> = (0..16).map(|i| (i * i + 7) % p).collect();
+        let mut transformed = data.clone();
+        transform(&mut transformed, &roots.forward);
+        let mut expected = data.clone();
+        ntt(&mut expected, p).unwrap();
+        assert_eq!(transformed, expected);
+        transform(&mut transformed, &roots.inverse);
+        for x in transformed.iter_mut() {
+            *x = mod_mul(*x, roots.n_inverse, p);
+        }
+        assert_eq!(transformed, data);
+
+        assert_eq!(ntt_roots(p, 12), Err(SubgroupError::NotPowerOfTwo));
+        assert_eq!(ntt_roots(17, 32), Err(SubgroupError::NotFactor));
+    }
+    // a wrong n is refused before generation and a wrong result after it
+    #[test]
+    fn test_not_factor_and_wrong_size() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::{
+            check_subgroup_params, check_subgroup_size, multiplicative_subgroup_with_stats, powers,
+            SubgroupBuilder,
+        };
+        // 5 does not divide 12, user error caught before anything is generated
+        assert_eq!(check_subgroup_params(13, 5), Err(SubgroupError::NotFactor));
+        assert_eq!(
+            multiplicative_subgroup_with_stats(13, 5).unwrap_err(),
+            SubgroupError::NotFactor
+        );
+        // 4 divides 12, but 3 has order 3 so its first 4 powers repeat, as a broken generator would give
+        assert_eq!(check_subgroup_params(13, 4), Ok(()));
+        assert_eq!(
+            check_subgroup_size(&powers(3, 4, 13), 4),
+            Err(SubgroupError::WrongSize)
+        );
+        assert_eq!(
+            check_subgroup_size(&[1, 5, 12], 4),
+            Err(SubgroupError::WrongSize)
+        );
+        assert_eq!(check_subgroup_size(&powers(5, 4, 13), 4), Ok(()));
+        // 0 generates {0}, not a subgroup at all, and the list has the right length
+        assert_eq!(
+            check_subgroup_size(&[1, 0, 0, 0], 4),
+            Err(SubgroupError::WrongSize)
+        );
+        let (subgroup, _) = multiplicative_subgroup_with_stats(13, 4).unwrap();
+        assert_eq!(check_subgroup_size(&subgroup, 4), Ok(()));
+        // a primality test that lets the composite 9 through: 4 divides 9 - 1 but every candidate
+        // the search accepts has g^2 of order below 4 modulo 9, so the built list falls short
+        assert_eq!(
+            SubgroupBuilder::new(9, 4).primality_test(|_| true).build(),
+            Err(SubgroupError::WrongSize)
+        );
+        assert_eq!(SubgroupBuilder::new(13, 4).build().map(|s| s.len()), Ok(4));
+        assert_ne!(
+            SubgroupError::NotFactor.to_string(),
+            SubgroupError::WrongSize.to_string()
+        );
+    }
+    // the low memory transform gives the same output as ntt
+    #[test]
+    fn test_ntt_low_memory() {
+        use crate::error::SubgroupError;
+        use crate::ntt::{ntt, ntt_low_memory};
+        let p = 998244353;
+        for n in [1, 2, 4, 256] {
+            let data: Vec<u64> = (0..n).map(|i| (i * i * 7919 + 3) % p).collect();
+            let mut expected = data.clone();
+            ntt(&mut expected, p).unwrap();
+            let mut low = data.clone();
+            ntt_low_memory(&mut low, p).unwrap();
+            assert_eq!(low, expected);
+        }
+        assert_eq!(
+            ntt_low_memory(&mut [1, 2, 3], p),
+            Err(SubgroupError::NotPowerOfTwo)
+        );
+    }
+    // supports_transform reports the first problem with p and n
+    #[test]
+    fn test_supports_transform() {
+        use crate::error::SubgroupError;
+        use crate::ntt::{intt, ntt, supports_transform, NttPlan};
+        let p = 998244353;
+        assert_eq!(supports_transform(p, 1), Ok(()));
+        assert_eq!(supports_transform(p, 1 << 23), Ok(()));
+        assert_eq!(supports_transform(17, 16), Ok(()));
+        assert_eq!(supports_transform(15, 2), Err(SubgroupError::NotPrime));
+        // p is checked first, even when n is wrong too
+        assert_eq!(supports_transform(15, 3), Err(SubgroupError::NotPrime));
+        assert_eq!(supports_transform(p, 3), Err(SubgroupError::NotPowerOfTwo));
+        assert_eq!(supports_transform(p, 0), Err(SubgroupError::NotPowerOfTwo));
+        assert_eq!(
+            supports_transform(p, 1 << 24),
+            Err(SubgroupError::NotFactor)
+        );
+        assert_eq!(supports_transform(13, 8), Err(SubgroupError::NotFactor));
+
+        assert_eq!(ntt(&mut [1; 8], 13), Err(SubgroupError::NotFactor));
+        assert_eq!(intt(&mut [1; 4], 15), Err(SubgroupError::NotPrime));
+        assert_eq!(NttPlan::new(p, 6), Err(SubgroupError::NotPowerOfTwo));
+    }
+    // the power of two construction gives the subgroup in bit reversed power order
+    #[test]
+    fn test_multiplicative_subgroup_pow2() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::multiplicative_subgroup_pow2;
+        for (p, n) in [(17, 1), (17, 2), (17, 16), (998244353, 1024), (13, 4)] {
+            let pow2 = multiplicative_subgroup_pow2(p, n).unwrap();
+            assert_eq!(pow2.len() as u64, n);
+            assert!(subgroups_equal(
+                &pow2,
+                &multiplicative_subgroup(p, n).unwrap(),
+                p
+            ));
+        }
+        // bit reversed power order, h = 2 generates the subgroup of size 8 modulo 17
+        let h = multiplicative_subgroup_pow2(17, 8).unwrap()[4];
+        assert_eq!(
+            multiplicative_subgroup_pow2(17, 8).unwrap(),
+            [0, 4, 2, 6, 1, 5, 3, 7].map(|e| mod_exp(h, e, 17))
+        );
+        assert_eq!(
+            multiplicative_subgroup_pow2(13, 3),
+            Err(SubgroupError::NotPowerOfTwo)
+        );
+        assert_eq!(
+            multiplicative_subgroup_pow2(13, 8),
+            Err(SubgroupError::NotFactor)
+        );
+    }
+    // residues above p/2 become negative and everything else is unchanged
+    #[test]
+    fn test_centered() {
+        use crate::multiplicative_subgruop::{to_centered, Subgroup};
+        assert_eq!(to_centered(0, 7), Ok(0));
+        assert_eq!(to_centered(3, 7), Ok(3));
+        assert_eq!(to_centered(4, 7), Ok(-3));
+        assert_eq!(to_centered(6, 7), Ok(-1));
+        assert_eq!(to_centered(5, 2), Ok(1));
+        assert_eq!(to_centered(u64::MAX - 1, u64::MAX - 58), Ok(57));
+        assert_eq!(to_centered(u64::MAX - 59, u64::MAX - 58), Ok(-1));
+
+        let p = 13;
+        let subgroup = Subgroup::new(p, 4).unwrap();
+        let centered = subgroup.centered().unwrap();
+        assert_eq!(centered.len(), 4);
+        for (&x, &v) in subgroup.iter().zip(&centered) {
+            assert!(-(p as i64) / 2 < v && v <= p as i64 / 2);
+            assert_eq!(v < 0, x > p / 2);
+            assert_eq!(v.rem_euclid(p as i64) as u64, x);
+        }
+        // the subgroup of size 4 is {1, 5, 8, 12}, centered {1, 5, -5, -1}
+        let mut sorted = centered.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, [-5, -1, 1, 5]);
+    }
+    // primitive roots modulo 2, 4, q^k and 2q^k, none for the other moduli
+    #[test]
+    fn test_primitive_root_mod() {
+        use crate::field::primitive_root_mod;
+        use crate::primitive_root::{carmichael_lambda, euler_totient, multiplicative_order};
+        for n in [
+            2,
+            4,
+            10,
+            9,
+            18,
+            25,
+            50,
+            27,
+            54,
+            1331,
+            2662,
+            98,
+            7 * 7 * 7 * 2,
+        ] {
+            let g = primitive_root_mod(n).unwrap();
+            assert_eq!(
+                multiplicative_order(g, n),
+                Some(carmichael_lambda(n)),
+                "n = {}",
+                n
+            );
+            assert_eq!(carmichael_lambda(n), euler_totient(n));
+            assert!(is_primitive_root_mod(g, n));
+        }
+        // 2 is the smallest primitive root modulo 5 but it is even, so 2 + 5 is used modulo 10
+        assert_eq!(primitive_root_mod(10), Some(7));
+        assert_eq!(primitive_root_mod(4), Some(3));
+        // 5 is the smallest primitive root modulo 40487 but 5^40486 = 1 modulo 40487^2, so 5 + 40487 is used
+        assert!(is_primitive_root_mod(5, 40487) && !is_primitive_root_mod(5, 40487 * 40487));
+        assert_eq!(primitive_root_mod(40487 * 40487), Some(5 + 40487));
+        assert!(is_primitive_root_mod(5 + 40487, 40487 * 40487));
+        assert_eq!(primitive_root_mod(1), Some(0));
+        for n in [0, 8, 12, 15, 16, 36, 100] {
+            assert_eq!(primitive_root_mod(n), None, "n = {}", n);
+        }
+    }
+    // the smallest prime whose least primitive root is above the bound
+    #[test]
+    fn test_prime_with_large_least_root() {
+        use crate::field::{prime_with_large_least_root, smallest_primitive_root};
+        assert_eq!(prime_with_large_least_root(0, 100), Some(2));
+        assert_eq!(prime_with_large_least_root(3, 100), Some(7));
+        assert_eq!(prime_with_large_least_root(4, 100), Some(23));
+        assert_eq!(prime_with_large_least_root(6, 100), Some(41));
+        assert_eq!(prime_with_large_least_root(7, 1000), Some(71));
+        assert_eq!(prime_with_large_least_root(8, 1000), Some(191));
+        assert_eq!(smallest_primitive_root(191), Some(19));
+        assert_eq!(prime_with_large_least_root(8, 190), None);
+        assert_eq!(prime_with_large_least_root(3, 6), None);
+    }
+    // the smallest subgroup containing every given element
+    #[test]
+    fn test_subgroup_closure() {
+        use crate::error::SubgroupError;
+        use crate::multiplicative_subgruop::{generated_subgroup, subgroup_closure};
+        assert_eq!(subgroup_closure(&[2], 7).unwrap(), [1, 2, 4]);
+        assert_eq!(subgroup_closure(&[], 7).unwrap(), [1]);
+        assert_eq!(subgroup_closure(&[9], 7).unwrap(), [1, 2, 4]);
+        // 2 has order 3 and 6 order 2 so together they give all of (Z/7Z)*
+        assert_eq!(subgroup_closure(&[2, 6], 7).unwrap(), [1, 2, 3, 4, 5, 6]);
+        // 5 and 3 have order 4 and 3 modulo 13, in a cyclic group they generate the subgroup of size lcm(4, 3)
+        let closure = subgroup_closure(&[5, 3], 13).unwrap();
+        assert_eq!(closure.len(), 12);
+        let closure = subgroup_closure(&[12, 3], 13).unwrap();
+        assert!(subgroups_equal(
+            &closure,
+            &multiplicative_subgroup(13, 6).unwrap(),
+            13
+        ));
+        let mut single = generated_subgroup(10, 101).unwrap();
+        single.sort_unstable();
+        assert_eq!(subgroup_closure(&[10], 101).unwrap(), single);
+
+        assert_eq!(
+            subgroup_closure(&[2, 14], 7),
+            Err(SubgroupError::ZeroElement)
+        );
+        assert_eq!(subgroup_closure(&[2], 8), Err(SubgroupError::NotPrime));
     }
 }